@@ -1,107 +1,77 @@
-use astar::astar::Node;
-use map::map::{Direction, Map, Position};
-use std::collections::{BinaryHeap, HashSet};
+use snake_core::astar::{encode_path, find_barrier_path};
+use snake_core::input::{check_in_board, check_no_overlap, check_range, parse_positions, parse_snake, InputError};
+use snake_core::map::{Direction, Map, Position, Snake};
+use snake_core::route::{is_walkable, stall_move};
 use wasm_bindgen::prelude::*;
 
-pub mod map;
-pub mod astar;
+/// T1 规定的棋盘边长
+const BOARD_SIZE: i32 = 8;
 
 #[wasm_bindgen]
 pub fn greedy_snake_move(input_snake: Vec<i32>, input_apple: Vec<i32>) -> i32 {
-    let mut snake:Vec<Position> = Vec::new();
-    let mut i = 0;
-    loop {
-        snake.push(Position::new(input_snake[i], input_snake[i + 1]));
-        i += 2;
-        if i == 8 {
-            break;
-        }
-    }
+    greedy_snake_move_sized(BOARD_SIZE, input_snake, input_apple)
+}
 
-    let apple = Position::new(input_apple[0], input_apple[1]);
-    let path = find_astar_path(&snake, &apple);
-    match path[0] {
-        Direction::Up => return 0,
-        Direction::Left => return 1,
-        Direction::Down => return 2,
-        Direction::Right => return 3,
+/// 任意边长棋盘上的 `greedy_snake_move`，蛇长由 `input_snake` 的长度决定
+///
+/// 暂时走不到苹果时跟着蛇尾兜圈子；往哪里走都会撞死时返回 -1，输入不合法时返回 `InputError::code` 给出的负数
+#[wasm_bindgen]
+pub fn greedy_snake_move_sized(board_size: i32, input_snake: Vec<i32>, input_apple: Vec<i32>) -> i32 {
+    let (snake, apple) = match process_input(board_size, &input_snake, &input_apple) {
+        Ok(input) => input,
+        Err(error) => return error.code(),
+    };
+    match plan(&Map::with_barriers(snake, vec![apple], vec![], board_size), &apple) {
+        Some((_, path)) => path[0].to_code(),
+        None => -1,
     }
-
-
 }
 
-fn find_astar_path(snake: &Vec<Position>, apple: &Position) -> Vec<Direction> {
-    let head = snake[0];
-    let goal = apple;
-
-    let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
-    let mut closed_set: HashSet<Position> = HashSet::new();
-    let node = Node::new((head).clone(), 0, Node::heuristic(&head, &apple), None);
-    open_set.push(node);
-
-    while !open_set.is_empty() {
-        let cur = match open_set.pop() {
-            Some(pos) => pos,
-            None => panic!("Got a None Node!"),
-        };
-        if cur.get_pos() == goal {
-            return reconstruct_path(&cur);
-        }
-
-        closed_set.insert((*cur.get_pos()).clone());
+/// 与 `greedy_snake_move` 参数相同，返回走到苹果的完整路线
+///
+/// 返回 `[步数, 苹果 x, 苹果 y, 每一步的方向编号...]`；兜圈子时路线只有一步，目标是走完这一步后蛇头的位置。
+/// 往哪里走都会撞死时返回 `[-1]`，输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn greedy_snake_move_path(input_snake: Vec<i32>, input_apple: Vec<i32>) -> Vec<i32> {
+    greedy_snake_move_path_sized(BOARD_SIZE, input_snake, input_apple)
+}
 
-        for direction in Direction::iter() {
-            let mut _neighbor = Position::new(-1, -1);
-            match direction {
-                Direction::Up => _neighbor = Position::up(cur.get_pos()),
-                Direction::Down => _neighbor = Position::down(cur.get_pos()),
-                Direction::Left => _neighbor = Position::left(cur.get_pos()),
-                Direction::Right => _neighbor = Position::right(cur.get_pos()),
-            }
-            if (!Map::edge_check(&_neighbor, &snake[0..=2])) && (!closed_set.contains(&_neighbor)){
-                let g = cur.get_g();
-                let h = Node::heuristic(&_neighbor, goal);
-                open_set.push(Node::new((_neighbor).clone(), g, h, Some((cur).clone())));
-            }
-        }
+/// 任意边长棋盘上的 `greedy_snake_move_path`
+#[wasm_bindgen]
+pub fn greedy_snake_move_path_sized(board_size: i32, input_snake: Vec<i32>, input_apple: Vec<i32>) -> Vec<i32> {
+    let (snake, apple) = match process_input(board_size, &input_snake, &input_apple) {
+        Ok(input) => input,
+        Err(error) => return vec![error.code()],
+    };
+    match plan(&Map::with_barriers(snake, vec![apple], vec![], board_size), &apple) {
+        Some((target, path)) => encode_path(&target, &path),
+        None => vec![-1],
     }
-
-
-    return vec![];
-
 }
 
-fn reconstruct_path(node: &Node) -> Vec<Direction> {
-    let mut path: Vec<Direction> = Vec::new();
-    let mut current_node = Some(node);
-
-    while let Some(node) = current_node {
-        if let Some(parent) = node.get_parent() {
-            let from = parent.get_pos(); 
-            let to = node.get_pos();
-            let mut _neighbor = Position::new(-1, -1);
-
-            for direction in Direction::iter() {
-                match direction {
-                    Direction::Up => _neighbor = Position::up(from),
-                    Direction::Down => _neighbor = Position::down(from),
-                    Direction::Left => _neighbor = Position::left(from),
-                    Direction::Right => _neighbor = Position::right(from),
-                }
-                if &_neighbor == to {
-                    path.push(direction);
-                    break;
-                }
-            }
-
-            current_node = Some(parent); 
-        } else {
-            break;
-        }
+/// 返回路线的目标和非空的路线
+///
+/// 寻路（`find_barrier_path`）按蛇身逐步让出来判断通行，但不管路上新长出来的蛇身，找到的路线可能会掉头撞上自己。
+/// 路线真的走得通才照着走，否则先跟着蛇尾兜一步，等蛇身让开；连兜圈子都做不到时仍按找到的路线碰碰运气
+fn plan(map: &Map, apple: &Position) -> Option<(Position, Vec<Direction>)> {
+    let path = find_barrier_path(map, apple);
+    if !path.is_empty() && is_walkable(map, &path) {
+        return Some((*apple, path));
+    }
+    let all: Vec<Direction> = Direction::iter().collect();
+    match stall_move(map, &all) {
+        Some(direction) => Some((map.get_my_snake().get_head().step(direction), vec![direction])),
+        None => (!path.is_empty()).then_some((*apple, path)),
     }
+}
 
-    path.reverse();
-    path
+fn process_input(board_size: i32, input_snake: &[i32], input_apple: &[i32]) -> Result<(Snake, Position), InputError> {
+    check_range("board_size", board_size, 1, i32::MAX)?;
+    let snake = parse_snake("snake", input_snake, None, board_size)?;
+    let apple = parse_positions("apple", input_apple, Some(1))?[0];
+    check_in_board("apple", &[apple], board_size)?;
+    check_no_overlap([snake.get_body(), &[apple]])?;
+    Ok((snake, apple))
 }
 
 #[cfg(test)]
@@ -111,9 +81,9 @@ mod tests {
 
     /// 进行一轮贪吃蛇游戏，直到撞墙、吃到苹果或判断无法吃到
     /// 如果顺利吃到了苹果，返回0；如果蛇死了，返回-1
-    fn game(snake: &mut Vec<i32>, apple: & Vec<i32>) -> i32 {
+    fn game(snake: &mut [i32], apple: &[i32]) -> i32 {
         loop {
-            let dir = greedy_snake_move(snake.clone(), apple.clone());
+            let dir = greedy_snake_move(snake.to_vec(), apple.to_vec());
             if dir == -1 {
                 return 1;
             }
@@ -146,7 +116,24 @@ mod tests {
             }
         }
     }
-    
+
+    /// 在 `board_size` 的棋盘上一直走到吃到苹果，不能撞墙或撞到自己，返回走的步数
+    fn eat_apple(board_size: i32, mut snake: Vec<i32>, apple: &[i32]) -> usize {
+        let mut steps = 0;
+        while snake[0..2] != *apple {
+            let dir = greedy_snake_move_sized(board_size, snake.clone(), apple.to_vec());
+            assert!((0..4).contains(&dir));
+            let (dx, dy) = [(0, 1), (-1, 0), (0, -1), (1, 0)][dir as usize];
+            let head = [snake[0] + dx, snake[1] + dy];
+            assert!(head.iter().all(|v| (1..=board_size).contains(v)), "撞墙了");
+            assert!(!snake[..snake.len() - 2].chunks(2).any(|body| body == head), "撞到了自己");
+            snake.rotate_right(2);
+            snake[0..2].copy_from_slice(&head);
+            steps += 1;
+            assert!(steps < 200, "一直没有吃到苹果");
+        }
+        steps
+    }
 
     #[test]
     /// 蛇卷曲为一个正方形形状，检验是否正确处理了蛇身碰撞
@@ -173,6 +160,39 @@ mod tests {
         assert_eq!(res, 0);
     }
 
+    #[test]
+    fn test_bigger_board_and_longer_snake() {
+        // 12×12 的棋盘，长度为 6 的蛇盘在左下角
+        let snake = vec![2, 1, 1, 1, 1, 2, 1, 3, 2, 3, 3, 3];
+        assert_eq!(eat_apple(12, snake, &[12, 12]), 21);
+    }
+
+    #[test]
+    fn test_stall_until_reachable() {
+        // A* 给出的路线 (4,2) -> (5,2) -> (4,2) 会掉头撞上自己，要先兜圈子等蛇身让开
+        let snake = vec![4, 1, 3, 1, 3, 2, 2, 2, 2, 3];
+        assert!(eat_apple(5, snake, &[2, 1]) < 50);
+    }
+
+    #[test]
+    fn test_full_path() {
+        let snake = vec![3, 1, 3, 2, 3, 3, 3, 4];
+        let path = greedy_snake_move_path(snake.clone(), vec![6, 1]);
+        assert_eq!(path, vec![3, 6, 1, 3, 3, 3]);
+        assert_eq!(path[3], greedy_snake_move(snake.clone(), vec![6, 1]));
+        assert_eq!(greedy_snake_move_path(snake, vec![9, 1]), vec![-4]);
+    }
+
+    #[test]
+    fn test_bad_input() {
+        assert_eq!(greedy_snake_move(vec![1, 1, 1, 2, 1], vec![5, 5]), -2);
+        assert_eq!(greedy_snake_move(vec![1, 1, 1, 2, 1, 3, 1, 4], vec![5]), -2);
+        assert_eq!(greedy_snake_move(vec![1, 1, 1, 2, 1, 4, 1, 5], vec![5, 5]), -3);
+        assert_eq!(greedy_snake_move(vec![1, 1, 1, 2, 1, 3, 1, 4], vec![9, 5]), -4);
+        assert_eq!(greedy_snake_move(vec![1, 1, 1, 2, 1, 3, 1, 4], vec![1, 3]), -5);
+        assert_eq!(greedy_snake_move_sized(0, vec![1, 1, 1, 2, 1, 3, 1, 4], vec![5, 5]), -6);
+    }
+
     #[test]
     fn test_edge() {
         let mut snake = vec![8, 8, 8, 7, 8, 6, 8, 5];
@@ -1,128 +1,138 @@
-use astar::astar::Node;
-use map::map::{Direction, Map, Position};
-use std::collections::{BinaryHeap, HashSet};
+use snake_core::astar::{encode_path, find_barrier_path};
+use snake_core::bitboard::{self, MAX_SIZE};
+use snake_core::input::{check_in_board, check_no_overlap, check_range, parse_positions, parse_snake, InputError};
+use snake_core::map::{Direction, Map, Position, Snake};
+use snake_core::route::{find_exact_path, plan_food_route, stall_move, Search};
 use wasm_bindgen::prelude::*;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-pub mod map;
-pub mod astar;
-pub mod random;
+/// T2 规定的棋盘边长
+const BOARD_SIZE: i32 = 8;
+/// `_sized` 接口支持的最大棋盘边长和最长蛇身，与位棋盘相同；寻路都在位棋盘上进行，再大的局面耗时会超出一回合的限制
+const MAX_BOARD_SIZE: i32 = MAX_SIZE;
+const MAX_SNAKE_LENGTH: i32 = bitboard::MAX_SNAKE_LENGTH as i32;
+/// 精确搜索展开的状态太多、没有结论时返回的编号，与 -1（确实走不到）和 `InputError::code` 的错误码都不同
+pub const UNDECIDED: i32 = -7;
 
 #[wasm_bindgen]
 pub fn greedy_snake_move_barriers(input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> i32 {
-    let (snake, apple, obstacles) = process_input(&input_snake, &input_apple, &input_obtacles);
-    let path = find_astar_path(&snake, &apple, &obstacles);
-    if path.len() > 0 {
-        match path[0] {
-            Direction::Up => return 0,
-            Direction::Left => return 1,
-            Direction::Down => return 2,
-            Direction::Right => return 3,
-        }
-    } else {
-        return -1;
-    }
+    greedy_snake_move_barriers_sized(BOARD_SIZE, input_snake, input_apple, input_obtacles)
 }
 
-fn process_input(input_snake: &Vec<i32>, input_apple: &Vec<i32>, input_obtacles: &Vec<i32>) -> (Vec<Position>, Position, Vec<Position>){
-    let mut snake:Vec<Position> = Vec::new();
-    let mut i = 0;
-    loop {
-        snake.push(Position::new(input_snake[i], input_snake[i + 1]));
-        i += 2;
-        if i == 8 { break; }
-    }
-    let apple = Position::new(input_apple[0], input_apple[1]);
-    let mut obstacles:Vec<Position> = Vec::new();
-    let mut i = 0;
-    loop {
-        obstacles.push(Position::new(input_obtacles[i], input_obtacles[i + 1]));
-        i += 2;
-        if i == 24 { break; }
+/// 边长不超过 `MAX_BOARD_SIZE` 的棋盘上的 `greedy_snake_move_barriers`，蛇长和障碍物数量由输入数组的长度决定
+///
+/// 沿最短路线走向苹果；苹果确实走不到时返回 -1（见 `is_apple_reachable`），
+/// 精确搜索没有结论时按 A* 路径走，没有路径就跟着蛇尾兜圈子，连这也做不到时返回 `UNDECIDED`；
+/// 输入不合法时返回 `InputError::code` 给出的负数
+#[wasm_bindgen]
+pub fn greedy_snake_move_barriers_sized(board_size: i32, input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> i32 {
+    let (snake, apple, obstacles) = match process_input(board_size, &input_snake, &input_apple, &input_obtacles, Some(1)) {
+        Ok((snake, apples, obstacles)) => (snake, apples[0], obstacles),
+        Err(error) => return error.code(),
+    };
+    let map = Map::with_barriers(snake, vec![apple], obstacles, board_size);
+    match find_exact_path(&map, &apple) {
+        Search::Found(path) => path.first().map_or(-1, |direction| direction.to_code()),
+        Search::Impossible => -1,
+        Search::Undecided => {
+            let path = find_barrier_path(&map, &apple);
+            let all: Vec<Direction> = Direction::iter().collect();
+            path.first().copied().or_else(|| stall_move(&map, &all)).map_or(UNDECIDED, |direction| direction.to_code())
+        }
     }
-
-    (snake, apple, obstacles)
-
 }
 
-fn find_astar_path(snake: &Vec<Position>, apple: &Position, obstacles: &Vec<Position>) -> Vec<Direction> {
-    let head = snake[0];
-    let goal = apple;
-
-    let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
-    let mut closed_set: HashSet<Position> = HashSet::new();
-    let node = Node::new((head).clone(), 0, Node::heuristic(&head, &apple), None);
-    open_set.push(node);
-
-    while !open_set.is_empty() {
-        let cur = match open_set.pop() {
-            Some(pos) => pos,
-            None => panic!("Got a None Node!"),
-        };
-        if cur.get_pos() == goal {
-            return reconstruct_path(&cur);
-        }
-
-        closed_set.insert((*cur.get_pos()).clone());
+/// 与 `greedy_snake_move_barriers` 参数相同，返回走到苹果的完整最短路线
+///
+/// 返回 `[步数, 苹果 x, 苹果 y, 每一步的方向编号...]`；苹果走不到时返回 `[-1]`，没有结论时返回 `[UNDECIDED]`，
+/// 输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn greedy_snake_move_barriers_path(input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> Vec<i32> {
+    greedy_snake_move_barriers_path_sized(BOARD_SIZE, input_snake, input_apple, input_obtacles)
+}
 
-        for direction in Direction::iter() {
-            let mut _neighbor = Position::new(-1, -1);
-            match direction {
-                Direction::Up => _neighbor = Position::up(cur.get_pos()),
-                Direction::Down => _neighbor = Position::down(cur.get_pos()),
-                Direction::Left => _neighbor = Position::left(cur.get_pos()),
-                Direction::Right => _neighbor = Position::right(cur.get_pos()),
-            }
-            if (!Map::colision_check(&_neighbor, &snake[0..=2], obstacles)) && (!closed_set.contains(&_neighbor)){
-                let g = cur.get_g();
-                let h = Node::heuristic(&_neighbor, goal);
-                open_set.push(Node::new((_neighbor).clone(), g, h, Some((cur).clone())));
-            }
-        }
+/// 边长不超过 `MAX_BOARD_SIZE` 的棋盘上的 `greedy_snake_move_barriers_path`
+#[wasm_bindgen]
+pub fn greedy_snake_move_barriers_path_sized(board_size: i32, input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> Vec<i32> {
+    let (snake, apple, obstacles) = match process_input(board_size, &input_snake, &input_apple, &input_obtacles, Some(1)) {
+        Ok((snake, apples, obstacles)) => (snake, apples[0], obstacles),
+        Err(error) => return vec![error.code()],
+    };
+    let map = Map::with_barriers(snake, vec![apple], obstacles, board_size);
+    match find_exact_path(&map, &apple) {
+        Search::Found(path) => encode_path(&apple, &path),
+        Search::Impossible => vec![-1],
+        Search::Undecided => vec![UNDECIDED],
     }
+}
 
+/// 苹果是否走得到：考虑蛇尾让出和蛇身跟随后的精确判断（见 `find_exact_path`）
+///
+/// 走得到返回 1，走不到返回 0，蛇太长、状态太多而没有结论时返回 `UNDECIDED`，
+/// 输入不合法时返回 `InputError::code` 给出的负数
+#[wasm_bindgen]
+pub fn is_apple_reachable(input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> i32 {
+    is_apple_reachable_sized(BOARD_SIZE, input_snake, input_apple, input_obtacles)
+}
 
-    return vec![];
-
+/// 边长不超过 `MAX_BOARD_SIZE` 的棋盘上的 `is_apple_reachable`
+#[wasm_bindgen]
+pub fn is_apple_reachable_sized(board_size: i32, input_snake: Vec<i32>, input_apple: Vec<i32>, input_obtacles: Vec<i32>) -> i32 {
+    let (snake, apple, obstacles) = match process_input(board_size, &input_snake, &input_apple, &input_obtacles, Some(1)) {
+        Ok((snake, apples, obstacles)) => (snake, apples[0], obstacles),
+        Err(error) => return error.code(),
+    };
+    let map = Map::with_barriers(snake, vec![apple], obstacles, board_size);
+    match find_exact_path(&map, &apple) {
+        Search::Found(_) => 1,
+        Search::Impossible => 0,
+        Search::Undecided => UNDECIDED,
+    }
 }
 
-fn reconstruct_path(node: &Node) -> Vec<Direction> {
-    let mut path: Vec<Direction> = Vec::new();
-    let mut current_node = Some(node);
-
-    while let Some(node) = current_node {
-        if let Some(parent) = node.get_parent() {
-            let from = parent.get_pos(); 
-            let to = node.get_pos();
-            let mut _neighbor = Position::new(-1, -1);
-
-            for direction in Direction::iter() {
-                match direction {
-                    Direction::Up => _neighbor = Position::up(from),
-                    Direction::Down => _neighbor = Position::down(from),
-                    Direction::Left => _neighbor = Position::left(from),
-                    Direction::Right => _neighbor = Position::right(from),
-                }
-                if &_neighbor == to {
-                    path.push(direction);
-                    break;
-                }
-            }
+/// Q2.5：场地上有 n 个不会刷新的果子，一次性规划吃完所有果子的路线
+///
+/// `input_fruits` 为 n 个果子的坐标，返回每一步的方向编号；
+/// 不存在能吃完所有果子的路线时返回 `[-1]`；果子太多、只能用贪心而贪心没找到路线时返回 `[UNDECIDED]`，
+/// 输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn greedy_snake_route_barriers(input_snake: Vec<i32>, input_fruits: Vec<i32>, input_obtacles: Vec<i32>) -> Vec<i32> {
+    greedy_snake_route_barriers_sized(BOARD_SIZE, input_snake, input_fruits, input_obtacles)
+}
 
-            current_node = Some(parent); 
-        } else {
-            break;
-        }
+/// 边长不超过 `MAX_BOARD_SIZE` 的棋盘上的 `greedy_snake_route_barriers`
+#[wasm_bindgen]
+pub fn greedy_snake_route_barriers_sized(board_size: i32, input_snake: Vec<i32>, input_fruits: Vec<i32>, input_obtacles: Vec<i32>) -> Vec<i32> {
+    let (snake, fruits, obstacles) = match process_input(board_size, &input_snake, &input_fruits, &input_obtacles, None) {
+        Ok(input) => input,
+        Err(error) => return vec![error.code()],
+    };
+    let map = Map::with_barriers(snake, fruits, obstacles, board_size);
+    match plan_food_route(&map) {
+        Search::Found(route) => route.moves.iter().map(|direction| direction.to_code()).collect(),
+        Search::Impossible => vec![-1],
+        Search::Undecided => vec![UNDECIDED],
     }
+}
 
-    path.reverse();
-    path
+/// 蛇身和障碍物都按输入数组的实际长度解析，蛇长不超过 `MAX_SNAKE_LENGTH`，不限定障碍物数量；
+/// `fruit_count` 给出时要求恰好有这么多个果子
+fn process_input(board_size: i32, input_snake: &[i32], input_fruits: &[i32], input_obtacles: &[i32], fruit_count: Option<usize>) -> Result<(Snake, Vec<Position>, Vec<Position>), InputError> {
+    check_range("board_size", board_size, 1, MAX_BOARD_SIZE)?;
+    let snake = parse_snake("snake", input_snake, None, board_size)?;
+    check_range("snake_length", i32::try_from(snake.get_body().len()).unwrap_or(i32::MAX), 1, MAX_SNAKE_LENGTH)?;
+    let fruits = parse_positions("fruits", input_fruits, fruit_count)?;
+    check_in_board("fruits", &fruits, board_size)?;
+    let obstacles = parse_positions("obstacles", input_obtacles, None)?;
+    check_in_board("obstacles", &obstacles, board_size)?;
+    check_no_overlap([snake.get_body(), &fruits, &obstacles])?;
+    Ok((snake, fruits, obstacles))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use random::RandomGenerator;
+    use snake_core::random::RandomGenerator;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     /// 检查位置是否与已有位置重叠
     fn is_position_overlap(x: i32, y: i32, positions: &[(i32, i32)]) -> bool {
@@ -130,29 +140,40 @@ mod tests {
     }
 
     /// 生成不重叠的随机位置
-    fn generate_unique_position(existing_positions: &[(i32, i32)]) -> (i32, i32) {
-        // 获取当前时间戳作为种子
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as u64;
-        let mut rng = RandomGenerator::new(timestamp);
-        
+    fn generate_unique_position(rng: &mut RandomGenerator, existing_positions: &[(i32, i32)]) -> (i32, i32) {
         loop {
-            let x = rng.generate_int(0, 10);
-            let y = rng.generate_int(0, 10);
+            // generate_int 只取低位，范围是 2 的幂时周期很短，先取一个奇数大小的范围再折回 1..=8
+            let x = rng.generate_int(0, 62) % 8 + 1;
+            let y = rng.generate_int(0, 62) % 8 + 1;
             if !is_position_overlap(x, y, existing_positions) {
                 return (x, y);
             }
         }
     }
 
+    /// 生成首尾相连的随机蛇身，走进死角时重新开始
+    fn generate_snake(rng: &mut RandomGenerator) -> Vec<(i32, i32)> {
+        'retry: loop {
+            let mut body = vec![generate_unique_position(rng, &[])];
+            while body.len() < 4 {
+                let (x, y) = body[body.len() - 1];
+                let next: Vec<(i32, i32)> = [(x, y + 1), (x - 1, y), (x, y - 1), (x + 1, y)].into_iter()
+                    .filter(|(x, y)| (1..=8).contains(x) && (1..=8).contains(y) && !is_position_overlap(*x, *y, &body))
+                    .collect();
+                if next.is_empty() {
+                    continue 'retry;
+                }
+                body.push(next[rng.generate_float(0.0, next.len() as f64) as usize % next.len()]);
+            }
+            return body;
+        }
+    }
 
     /// 进行一轮贪吃蛇游戏，直到撞墙、吃到苹果或判断无法吃到
     /// 如果顺利吃到了苹果，返回0；如果蛇死了，返回-1
-    fn game(snake: &mut Vec<i32>, apple: &Vec<i32>, obstacles: &Vec<i32>) -> i32 {
+    fn game(snake: &mut [i32], apple: &[i32], obstacles: &[i32]) -> i32 {
         loop {
-            let dir = greedy_snake_move_barriers(snake.clone(), apple.clone(), obstacles.clone());
+            let dir = greedy_snake_move_barriers(snake.to_vec(), apple.to_vec(), obstacles.to_vec());
             if dir == -1 {
                 return 1;
             }
@@ -208,28 +229,140 @@ mod tests {
         assert_eq!(result, 1); // 应该返回1，表示无法吃到苹果
     }
 
+    #[test]
+    fn test_route_eats_all_fruits() {
+        let mut snake = vec![5, 5, 5, 6, 5, 7, 5, 8];
+        let fruits = vec![1, 1, 8, 1, 3, 8, 7, 6];
+        let obstacles = vec![1, 2, 1, 3, 1, 4, 1, 5, 1, 6, 1, 7, 1, 8, 3, 3, 4, 4, 4, 5, 4, 6, 4, 7];
+        let route = greedy_snake_route_barriers(snake.clone(), fruits.clone(), obstacles.clone());
+
+        let mut eaten = vec![false; fruits.len() / 2];
+        for dir in route {
+            assert!((0..4).contains(&dir));
+            let head = [
+                snake[0] + if dir == 3 { 1 } else { 0 } - if dir == 1 { 1 } else { 0 },
+                snake[1] + if dir == 0 { 1 } else { 0 } - if dir == 2 { 1 } else { 0 },
+            ];
+            assert!(head[0] >= 1 && head[0] <= 8 && head[1] >= 1 && head[1] <= 8, "撞墙了");
+            assert!(!obstacles.chunks(2).any(|ob| ob == head), "撞到了障碍物");
+            assert!(!snake[0..6].chunks(2).any(|body| body == head), "撞到了自己");
+            snake.rotate_right(2);
+            snake[0..2].copy_from_slice(&head);
+            if let Some(i) = fruits.chunks(2).position(|fruit| fruit == head) {
+                eaten[i] = true;
+            }
+        }
+        assert!(eaten.iter().all(|e| *e));
+    }
+
+    #[test]
+    fn test_route_impossible() {
+        // (1,1) 被障碍物围住，吃不完
+        let snake = vec![5, 5, 5, 6, 5, 7, 5, 8];
+        let fruits = vec![1, 1, 8, 1];
+        let obstacles = vec![1, 2, 1, 3, 1, 4, 1, 5, 1, 6, 1, 7, 1, 8, 2, 1, 4, 4, 4, 5, 4, 6, 4, 7];
+        assert_eq!(greedy_snake_route_barriers(snake, fruits, obstacles), vec![-1]);
+    }
+
+    #[test]
+    fn test_apple_reachable() {
+        let snake = vec![5, 5, 5, 6, 5, 7, 5, 8];
+        let obstacles = vec![1, 2, 1, 3, 1, 4, 1, 5, 1, 6, 1, 7, 1, 8, 2, 1, 4, 4, 4, 5, 4, 6, 4, 7];
+        assert_eq!(is_apple_reachable(snake.clone(), vec![1, 1], obstacles.clone()), 0);
+        assert_eq!(is_apple_reachable(snake.clone(), vec![8, 1], obstacles.clone()), 1);
+        assert_eq!(is_apple_reachable(snake, vec![4, 4], obstacles), -5);
+        // (1,1) 只能从蛇尾所在的 (1,2) 进去，蛇身让出之后才走得到
+        let snake = vec![3, 2, 2, 2, 1, 2];
+        assert_eq!(is_apple_reachable_sized(4, snake.clone(), vec![1, 1], vec![2, 1]), 1);
+        assert_ne!(greedy_snake_move_barriers_sized(4, snake, vec![1, 1], vec![2, 1]), -1);
+    }
+
+    #[test]
+    fn test_full_path() {
+        // 沿着路线走，每一步都与逐步调用 `greedy_snake_move_barriers` 的结果一致
+        let mut snake = vec![5, 5, 5, 6, 5, 7, 5, 8];
+        let obstacles = vec![1, 2, 1, 3, 1, 4, 1, 5, 1, 6, 1, 7, 1, 8, 2, 1, 4, 4, 4, 5, 4, 6, 4, 7];
+        let path = greedy_snake_move_barriers_path(snake.clone(), vec![8, 1], obstacles.clone());
+        assert_eq!(path[0] as usize, path.len() - 3);
+        assert_eq!(path[1..3], [8, 1]);
+        for dir in &path[3..] {
+            assert_eq!(greedy_snake_move_barriers(snake.clone(), vec![8, 1], obstacles.clone()), *dir);
+            let head = [
+                snake[0] + if *dir == 3 { 1 } else { 0 } - if *dir == 1 { 1 } else { 0 },
+                snake[1] + if *dir == 0 { 1 } else { 0 } - if *dir == 2 { 1 } else { 0 },
+            ];
+            snake.rotate_right(2);
+            snake[0..2].copy_from_slice(&head);
+        }
+        assert_eq!(snake[0..2], [8, 1]);
+        assert_eq!(greedy_snake_move_barriers_path(vec![5, 5, 5, 6, 5, 7, 5, 8], vec![1, 1], obstacles), vec![-1]);
+    }
+
+    #[test]
+    fn test_bad_input() {
+        let snake = vec![5, 5, 5, 6, 5, 7, 5, 8];
+        let obstacles = vec![1, 2, 1, 3, 1, 4, 1, 5, 1, 6, 1, 7, 1, 8, 2, 1, 4, 4, 4, 5, 4, 6, 4, 7];
+        assert_eq!(greedy_snake_move_barriers(snake.clone(), vec![1, 1], obstacles[1..].to_vec()), -2);
+        assert_eq!(greedy_snake_move_barriers(snake.clone(), vec![4, 4], obstacles.clone()), -5);
+        assert_eq!(greedy_snake_move_barriers(vec![5, 5, 5, 6, 5, 8, 5, 9], vec![1, 1], obstacles.clone()), -4);
+        assert_eq!(greedy_snake_move_barriers(vec![5, 5, 5, 6, 5, 8, 5, 7], vec![1, 1], obstacles.clone()), -3);
+        assert_eq!(greedy_snake_route_barriers(snake, vec![1, 1, 8], obstacles), vec![-2]);
+    }
+
+    #[test]
+    fn test_sized_board() {
+        // 10×10 的棋盘，长度为 5 的蛇，只有 3 个障碍物；(10,10) 被围住
+        let snake = vec![5, 5, 5, 6, 5, 7, 5, 8, 5, 9];
+        let obstacles = vec![9, 10, 10, 9, 9, 9];
+        assert_eq!(greedy_snake_move_barriers_sized(10, snake.clone(), vec![10, 10], obstacles.clone()), -1);
+        assert_ne!(greedy_snake_move_barriers_sized(10, snake.clone(), vec![10, 1], obstacles.clone()), -1);
+        // 8×8 时 (10,1) 在棋盘外，属于不合法输入
+        assert_eq!(greedy_snake_move_barriers_sized(8, snake.clone(), vec![10, 1], obstacles.clone()), -4);
+
+        let route = greedy_snake_route_barriers_sized(10, snake, vec![10, 1, 1, 10], obstacles);
+        assert_ne!(route, vec![-1]);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        // 14 节的蛇，(10,10) 被围住；精确搜索有状态上限，很快停下来，但不能把没有结论当成走不到
+        let mut snake = vec![4, 2, 3, 2, 2, 2, 1, 2];
+        snake.extend((1..=10).flat_map(|x| [x, 1]));
+        assert_eq!(is_apple_reachable_sized(10, snake.clone(), vec![10, 10], vec![9, 10, 10, 9]), UNDECIDED);
+        assert_eq!(greedy_snake_move_barriers_path_sized(10, snake.clone(), vec![10, 10], vec![9, 10, 10, 9]), vec![UNDECIDED]);
+        // 走一步还是要给的：没有 A* 路径时跟着蛇尾兜圈子
+        let direction = greedy_snake_move_barriers_sized(10, snake, vec![10, 10], vec![9, 10, 10, 9]);
+        assert!((0..4).contains(&direction));
+
+        assert_eq!(is_apple_reachable_sized(MAX_BOARD_SIZE + 1, vec![1, 1, 1, 2], vec![5, 5], vec![]), -6);
+        // 占满最下面一行再拐上去一节，比上限多一节
+        let mut long: Vec<i32> = (1..=MAX_BOARD_SIZE).flat_map(|x| [x, 1]).collect();
+        long.extend([MAX_BOARD_SIZE, 2]);
+        assert_eq!(is_apple_reachable_sized(MAX_BOARD_SIZE, long, vec![5, 5], vec![]), -6);
+    }
+
     #[test]
     fn random_test() {
-        let mut positions = Vec::new();
-        
+        // 获取当前时间戳作为种子
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let mut rng = RandomGenerator::new(timestamp);
+
         // 生成蛇的位置（4个段）
-        let mut snake = Vec::new();
-        for _ in 0..4 {
-            let (x, y) = generate_unique_position(&positions);
-            positions.push((x, y));
-            snake.push(x);
-            snake.push(y);
-        }
+        let mut positions = generate_snake(&mut rng);
+        let mut snake: Vec<i32> = positions.iter().flat_map(|(x, y)| [*x, *y]).collect();
         
         // 生成苹果位置
-        let (apple_x, apple_y) = generate_unique_position(&positions);
+        let (apple_x, apple_y) = generate_unique_position(&mut rng, &positions);
         positions.push((apple_x, apple_y));
         let apple = vec![apple_x, apple_y];
         
         // 生成障碍物位置（12个）
         let mut obstacles = Vec::new();
         for _ in 0..12 {
-            let (x, y) = generate_unique_position(&positions);
+            let (x, y) = generate_unique_position(&mut rng, &positions);
             positions.push((x, y));
             obstacles.push(x);
             obstacles.push(y);
@@ -0,0 +1,124 @@
+//! `T3/board/pkg` 的源码版本，用 `wasm-pack build --target nodejs --out-dir ../board/pkg` 替换预编译模块
+//!
+//! 输入不合法时不 panic，按 `InputError::code` 返回负数的错误码，与 T1/T2 的导出函数一致
+
+use snake_core::board::{self, DeathCause, SNAKE_STRIDE};
+use snake_core::input::InputError;
+use snake_core::map::{Direction, Position};
+use snake_core::replay::{Death, Move, Replay, Turn};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct OutputBuffer {
+    status: Vec<i32>,
+    snakes: Vec<i32>,
+    foods: Vec<i32>,
+    error: i32,
+}
+
+/// `snake-engine.js` 直接从 wasm 内存中读取这三段数组，这里只返回它们的地址
+#[wasm_bindgen]
+impl OutputBuffer {
+    /// 输入合法时为 0，否则为 `InputError::code` 给出的负数，这时三段数组都是空的
+    pub fn error(&self) -> i32 {
+        self.error
+    }
+
+    pub fn status(&self) -> *const i32 {
+        self.status.as_ptr()
+    }
+
+    pub fn snakes(&self) -> *const i32 {
+        self.snakes.as_ptr()
+    }
+
+    pub fn foods(&self) -> *const i32 {
+        self.foods.as_ptr()
+    }
+}
+
+#[wasm_bindgen]
+pub fn process_turn(n: i32, snakes: Vec<i32>, actions: Vec<i32>, foods: Vec<i32>, seed: u64) -> OutputBuffer {
+    match board::process_turn(n, &snakes, &actions, &foods, seed) {
+        Ok(output) => OutputBuffer { status: output.status, snakes: output.snakes, foods: output.foods, error: 0 },
+        Err(error) => OutputBuffer { status: vec![], snakes: vec![], foods: vec![], error: error.code() },
+    }
+}
+
+/// 输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn initial_foods(n: i32, snakes: Vec<i32>, seed: u64, required_count: i32) -> Vec<i32> {
+    board::initial_foods(n, &snakes, seed, required_count).unwrap_or_else(|error| vec![error.code()])
+}
+
+/// 与 `process_turn` 参数相同，给出每条蛇的死亡原因编号（见 `DeathCause::to_code`），存活的蛇为 -1；
+/// 输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn death_causes(n: i32, snakes: Vec<i32>, actions: Vec<i32>) -> Vec<i32> {
+    try_death_causes(n, &snakes, &actions).unwrap_or_else(|error| vec![error.code()])
+}
+
+fn try_death_causes(n: i32, snakes: &[i32], actions: &[i32]) -> Result<Vec<i32>, InputError> {
+    let snakes = board::parse_snakes(snakes)?;
+    let directions: Vec<Option<Direction>> = actions.iter().map(|a| Direction::from_code(*a)).collect();
+    board::check_actions(&snakes, &directions)?;
+    Ok(board::death_causes(n, &snakes, &directions).into_iter()
+        .map(|cause| cause.map_or(-1, DeathCause::to_code))
+        .collect())
+}
+
+/// 在 `snake-engine.js` 中记录回放，格式见 `snake_core::replay`
+#[wasm_bindgen]
+pub struct ReplayRecorder {
+    replay: Replay,
+}
+
+#[wasm_bindgen]
+impl ReplayRecorder {
+    /// `initial_snakes` 按座位排列，每条 8 个 i32；策略名先记为空，用 `set_strategy` 补上
+    #[wasm_bindgen(constructor)]
+    pub fn new(n: i32, seed: u64, max_rounds: i32, initial_snakes: Vec<i32>, initial_foods: Vec<i32>) -> ReplayRecorder {
+        let initial_snakes: Vec<Vec<Position>> = initial_snakes.chunks(SNAKE_STRIDE).map(positions).collect();
+        let replay = Replay {
+            board_size: n,
+            seed,
+            max_rounds,
+            food_count: initial_foods.len() / 2,
+            strategies: vec![String::new(); initial_snakes.len()],
+            initial_snakes,
+            initial_foods: positions(&initial_foods),
+            turns: vec![],
+        };
+        ReplayRecorder { replay }
+    }
+
+    pub fn set_strategy(&mut self, seat: usize, name: String) {
+        if let Some(slot) = self.replay.strategies.get_mut(seat) {
+            *slot = name;
+        }
+    }
+
+    /// 记录一个回合：`seats` 为这一回合开始时存活的座位，`actions` 和 `decision_ms` 与之一一对应；
+    /// `causes` 为 `death_causes` 的结果，`food_spawns` 为回合结束时新补充的果子
+    pub fn record_turn(&mut self, seats: Vec<u32>, actions: Vec<i32>, decision_ms: Vec<f64>, causes: Vec<i32>, food_spawns: Vec<i32>) {
+        let moves = seats.iter().zip(actions).zip(decision_ms)
+            .map(|((seat, action), decision_ms)| Move { seat: *seat as usize, action, decision_ms })
+            .collect();
+        let deaths = seats.iter().zip(causes)
+            .filter_map(|(seat, code)| DeathCause::from_code(code).map(|cause| Death { seat: *seat as usize, cause }))
+            .collect();
+        self.replay.turns.push(Turn { moves, deaths, food_spawns: positions(&food_spawns) });
+    }
+
+    pub fn to_json(&self) -> String {
+        self.replay.to_json()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.replay.to_bytes()
+    }
+}
+
+fn positions(coords: &[i32]) -> Vec<Position> {
+    coords.chunks_exact(2).map(|pair| Position::new(pair[0], pair[1])).collect()
+}
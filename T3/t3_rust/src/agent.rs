@@ -0,0 +1,387 @@
+//! 跨回合保留状态的 T3 决策对象
+//!
+//! 导出函数每回合都从头解析局面、重新寻路，上一回合算出的东西全部丢掉。`SnakeAgent` 在一局中只构造一次，
+//! 保留上一回合的路线、跟踪其他蛇的对手模型和 MCTS 搜索树：果子没有变化、路线在新局面下仍然走得通时直接沿用，
+//! 新局面恰好是搜索树中的子节点时接着搜。无状态的导出函数每次构造一个新的 `SnakeAgent`。
+
+use crate::trace::{mark_outscored, note, set_branch, Branch, Candidate, DecisionTrace, Rejection};
+use crate::{greedy_plan, keeps_space, now_ms, process_input, MAX_SEARCH_DEPTH, SEARCH_BUDGET_MS};
+use snake_core::astar::encode_path;
+use snake_core::map::{Direction, Map, Position, Snake};
+use snake_core::mcts::{MctsBudget, MctsConfig, MctsTree};
+use snake_core::opponent::OpponentModel;
+use snake_core::risk::{assess_moves, MoveRisk, RiskLevel};
+use snake_core::search::{search, SearchState};
+use wasm_bindgen::prelude::*;
+
+/// 上一回合留下的路线
+struct Plan {
+    /// 剩下的步骤，第一步就是这一回合要走的
+    moves: Vec<Direction>,
+    target: Position,
+    /// 按路线走完上一步后蛇头应该在的位置
+    head: Position,
+    /// 规划时场上的果子，按坐标排好序
+    foods: Vec<Position>,
+}
+
+impl Plan {
+    /// 蛇头按计划到位、果子没变、第一步没有被对抗搜索排除，剩下的回合还走得完，并且剩下的路线按 A* 的标准仍然走得通
+    fn is_valid(&self, map: &Map, allowed: &[Direction], foods: &[Position], rounds_left: i32) -> bool {
+        let head = *map.get_my_snake().get_head();
+        if head != self.head || foods != self.foods || !allowed.contains(&self.moves[0]) || self.moves.len() as i32 > rounds_left
+            || !keeps_space(map, self.moves[0], rounds_left) {
+            return false;
+        }
+        let mut pos = head;
+        self.moves.iter().zip(1..).all(|(direction, steps)| {
+            pos = pos.step(*direction);
+            !map.colision_check_at(&pos, steps)
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub struct SnakeAgent {
+    board_size: i32,
+    plan: Option<Plan>,
+    opponents: OpponentModel,
+    tree: Option<MctsTree>,
+    replans: u32,
+    /// 对抗搜索的最大深度和时间预算（毫秒），默认为 `MAX_SEARCH_DEPTH` 和 `SEARCH_BUDGET_MS`
+    search_depth: i32,
+    search_budget_ms: f64,
+    /// 上一次 `step` 选中的方向的风险等级
+    last_risk: Option<RiskLevel>,
+    /// 是否记录决策过程，见 `trace`
+    tracing: bool,
+    /// 上一次决策的记录，没打开记录时为 `None`
+    trace: Option<DecisionTrace>,
+}
+
+#[wasm_bindgen]
+impl SnakeAgent {
+    #[wasm_bindgen(constructor)]
+    pub fn new(board_size: i32) -> SnakeAgent {
+        SnakeAgent {
+            board_size,
+            plan: None,
+            opponents: OpponentModel::new(),
+            tree: None,
+            replans: 0,
+            search_depth: MAX_SEARCH_DEPTH,
+            search_budget_ms: SEARCH_BUDGET_MS,
+            last_risk: None,
+            tracing: false,
+            trace: None,
+        }
+    }
+
+    /// 与 `greedy_snake_step` 相同的决策，上一回合的路线仍然可用时不重新规划
+    pub fn step(&mut self, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> i32 {
+        match self.plan_step(&input_my_snake, snake_num, &input_other_snakes, food_num, &input_foods, round) {
+            Ok((_, moves)) => moves[0].to_code(),
+            Err(code) => code,
+        }
+    }
+
+    /// 与 `step` 相同，返回格式与 `greedy_snake_step_path` 相同的完整路线
+    pub fn step_path(&mut self, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> Vec<i32> {
+        match self.plan_step(&input_my_snake, snake_num, &input_other_snakes, food_num, &input_foods, round) {
+            Ok((target, moves)) => encode_path(&target, &moves),
+            Err(code) => vec![code],
+        }
+    }
+
+    /// 与 `mcts_snake_step_with_budget` 相同的决策，能复用上一回合的搜索树时接着搜
+    ///
+    /// 局面放不进位棋盘（见 `BoardState::fits`）时与 `step` 的决策相同
+    #[allow(clippy::too_many_arguments)]
+    pub fn mcts_step(&mut self, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32, max_iterations: u32, max_ms: f64) -> i32 {
+        self.trace = self.tracing.then(DecisionTrace::default);
+        let (my_snake, other_snakes, foods) = match self.observe(&input_my_snake, snake_num, &input_other_snakes, food_num, &input_foods) {
+            Ok(input) => input,
+            Err(code) => return code,
+        };
+
+        // 种子由局面决定，同样的局面总是得到同样的决策，方便复现
+        let seed = input_my_snake.iter().chain(&input_other_snakes).chain(&input_foods)
+            .fold(round as u64, |hash, v| hash.wrapping_mul(31).wrapping_add(*v as u64));
+        let config = MctsConfig { budget: MctsBudget { max_iterations, max_ms }, seed, ..MctsConfig::default() };
+        let head = *my_snake.get_head();
+        // 局面放不进位棋盘时没法模拟，改用 `step` 的贪心决策
+        let Some(root) = SearchState::try_new(my_snake.clone(), other_snakes.clone(), foods.clone(), self.board_size, round) else {
+            return self.decide(my_snake, other_snakes, foods, round).1[0].to_code();
+        };
+        let mut tree = self.tree.take()
+            .and_then(|mut tree| tree.advance(&root).then_some(tree))
+            .unwrap_or_else(|| MctsTree::new(root));
+        let result = tree.run(&config, &now_ms);
+        self.tree = Some(tree);
+
+        let direction = result.best_direction().unwrap_or(Direction::Up);
+        for (candidate, visits, _) in &result.stats {
+            note(&mut self.trace, || Candidate { branch: Branch::Mcts, direction: *candidate, target: head.step(*candidate), score: *visits as f64, rejection: None });
+        }
+        mark_outscored(&mut self.trace, Branch::Mcts, direction);
+        if let Some(trace) = &mut self.trace {
+            trace.branch = Some(Branch::Mcts);
+            trace.path = vec![direction];
+            trace.target = Some(head.step(direction));
+        }
+        direction.to_code()
+    }
+
+    /// 修改 `step` 中对抗搜索的预算，深度和时间任一用完就停止
+    ///
+    /// 时间预算传 `Infinity` 时只按深度搜索，决策与机器快慢无关，本地评测用它复现对局
+    pub fn set_search_budget(&mut self, max_depth: i32, max_ms: f64) {
+        self.search_depth = max_depth;
+        self.search_budget_ms = max_ms;
+    }
+
+    /// 打开或关闭决策记录，打开后每次决策都会留下 `last_trace_json` 能取到的记录
+    pub fn set_tracing(&mut self, enabled: bool) {
+        self.tracing = enabled;
+        if !enabled {
+            self.trace = None;
+        }
+    }
+
+    /// 上一次决策的记录（格式见 `DecisionTrace::to_json`），没打开记录时为空字符串
+    pub fn last_trace_json(&self) -> String {
+        self.trace.as_ref().map_or(String::new(), DecisionTrace::to_json)
+    }
+
+    /// 重新规划路线的次数
+    #[wasm_bindgen(getter)]
+    pub fn replans(&self) -> u32 {
+        self.replans
+    }
+
+    /// 上一次 `step` 选中的方向的风险等级，编号见 `RiskLevel::to_code`，还没有决策过时为 -1
+    #[wasm_bindgen(getter)]
+    pub fn last_risk_level(&self) -> i32 {
+        self.last_risk.map_or(-1, RiskLevel::to_code)
+    }
+
+    /// 已经观察过的回合数
+    #[wasm_bindgen(getter)]
+    pub fn observed_rounds(&self) -> usize {
+        self.opponents.get_observations()
+    }
+}
+
+impl SnakeAgent {
+    /// 上一次决策的记录，没打开记录时为 `None`
+    pub fn last_trace(&self) -> Option<&DecisionTrace> {
+        self.trace.as_ref()
+    }
+
+    /// 解析这一回合的输入并交给对手模型，输入不合法时返回错误码
+    fn observe(&mut self, input_my_snake: &[i32], snake_num: i32, input_other_snakes: &[i32], food_num: i32, input_foods: &[i32]) -> Result<(Snake, Vec<Snake>, Vec<Position>), i32> {
+        let (my_snake, other_snakes, foods) = match process_input(self.board_size, input_my_snake, snake_num, input_other_snakes, food_num, input_foods) {
+            Ok(input) => input,
+            Err(error) => {
+                let code = error.code();
+                if let Some(trace) = &mut self.trace {
+                    trace.input_error = Some(error);
+                }
+                return Err(code);
+            }
+        };
+        self.opponents.observe(&my_snake, &other_snakes, &foods, self.board_size);
+        Ok((my_snake, other_snakes, foods))
+    }
+
+    /// `step` 的决策过程，返回路线的目标和非空的路线
+    fn plan_step(&mut self, input_my_snake: &[i32], snake_num: i32, input_other_snakes: &[i32], food_num: i32, input_foods: &[i32], round: i32) -> Result<(Position, Vec<Direction>), i32> {
+        self.trace = self.tracing.then(DecisionTrace::default);
+        let (my_snake, other_snakes, foods) = self.observe(input_my_snake, snake_num, input_other_snakes, food_num, input_foods)?;
+        Ok(self.decide(my_snake, other_snakes, foods, round))
+    }
+
+    /// `plan_step` 解析完输入之后的部分
+    fn decide(&mut self, my_snake: Snake, other_snakes: Vec<Snake>, foods: Vec<Position>, round: i32) -> (Position, Vec<Direction>) {
+        // 先用对抗搜索排除最坏情况下会吃亏的方向，再在剩下的方向里按贪心规则挑选；
+        // 局面放不进位棋盘（比如棋盘超过 16×16）时不搜索，所有方向都交给贪心规则
+        let allowed = match SearchState::try_new(my_snake.clone(), other_snakes.clone(), foods.clone(), self.board_size, round) {
+            Some(root) => {
+                let result = search(&root, self.search_depth, self.search_budget_ms, &now_ms);
+                if let Some(trace) = &mut self.trace {
+                    trace.search_depth = result.depth;
+                    trace.search_values = result.values.clone();
+                }
+                result.best_directions()
+            }
+            None => Direction::iter().collect(),
+        };
+        if let Some(trace) = &mut self.trace {
+            trace.allowed = allowed.clone();
+        }
+
+        let mut sorted_foods = foods.clone();
+        sorted_foods.sort_by_key(|food| (food.get_x(), food.get_y()));
+        let map = Map::with_head_risks(my_snake, other_snakes, foods, self.board_size, &self.opponents.head_risks());
+        let (mut target, mut moves) = match self.plan.take().filter(|plan| plan.is_valid(&map, &allowed, &sorted_foods, round)) {
+            Some(plan) => {
+                set_branch(&mut self.trace, Branch::ReusePlan);
+                (plan.target, plan.moves)
+            }
+            None => {
+                self.replans += 1;
+                greedy_plan(&map, &allowed, round, &mut self.trace)
+            }
+        };
+
+        // 对抗搜索按最坏情况把可能头对头的方向都排除了；按对手模型估计的期望收益再比一比，更划算时改走那一步。
+        // 这是有意的赌博：对抗搜索排除的方向只有这一步就能吃到果子、风险模型判断不是必死、走过去还有地方可去时才考虑，
+        // 比如最后几回合死了也不亏时去抢对手也够得着的果子；不吃果子的方向仍然以对抗搜索为准
+        let head = *map.get_my_snake().get_head();
+        let risks = assess_moves(&map, &self.opponents, round);
+        let chosen = risks.iter().find(|risk| risk.direction == moves[0]);
+        let rejection_of = |risk: &MoveRisk| if risk.level == RiskLevel::Deadly {
+            Some(Rejection::Deadly)
+        } else if !allowed.contains(&risk.direction) && !risk.food {
+            Some(Rejection::NotAllowed)
+        } else if !keeps_space(&map, risk.direction, round) {
+            Some(Rejection::NoSpace)
+        } else {
+            None
+        };
+        let better = risks.iter()
+            .filter(|risk| rejection_of(risk).is_none())
+            .max_by(|a, b| a.value.total_cmp(&b.value))
+            .filter(|risk| chosen.is_none_or(|chosen| risk.value > chosen.value));
+        for risk in &risks {
+            note(&mut self.trace, || Candidate { branch: Branch::RiskOverride, direction: risk.direction, target: head.step(risk.direction), score: risk.value, rejection: rejection_of(risk) });
+        }
+        let level = match better {
+            Some(risk) => {
+                set_branch(&mut self.trace, Branch::RiskOverride);
+                target = head.step(risk.direction);
+                moves = vec![risk.direction];
+                Some(risk.level)
+            }
+            None => chosen.map(|risk| risk.level),
+        };
+        mark_outscored(&mut self.trace, Branch::RiskOverride, moves[0]);
+        self.last_risk = level;
+        if let Some(trace) = &mut self.trace {
+            trace.path = moves.clone();
+            trace.target = Some(target);
+            trace.risk = level;
+        }
+
+        // 只有一步的路线（比如没有果子可去时）不必留到下一回合
+        self.plan = (moves.len() > 1).then(|| Plan { moves: moves[1..].to_vec(), target, head: head.step(moves[0]), foods: sorted_foods });
+        (target, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按方向编号移动蛇，长度不变
+    fn moved(snake: &[i32], dir: i32) -> Vec<i32> {
+        let direction = Direction::from_code(dir).unwrap();
+        let head = Position::new(snake[0], snake[1]).step(direction);
+        let mut next = vec![head.get_x(), head.get_y()];
+        next.extend_from_slice(&snake[..snake.len() - 2]);
+        next
+    }
+
+    #[test]
+    fn test_reuse_plan() {
+        let mut agent = SnakeAgent::new(8);
+        let mut snake = vec![1, 4, 1, 3, 1, 2, 1, 1];
+        let foods = vec![8, 8];
+        for round in 0..6 {
+            let dir = agent.step(snake.clone(), 0, vec![], 1, foods.clone(), 50 - round);
+            snake = moved(&snake, dir);
+        }
+        assert_eq!(agent.replans(), 1);
+        assert_eq!(agent.observed_rounds(), 6);
+
+        // 刷出新的果子后重新规划
+        let dir = agent.step(snake.clone(), 0, vec![], 2, vec![8, 8, 1, 8], 44);
+        assert!((0..4).contains(&dir));
+        assert_eq!(agent.replans(), 2);
+        // 没有照着路线走也要重新规划
+        let other = (0..4).map(|d| moved(&snake, d))
+            .find(|next| next[..2] != moved(&snake, dir)[..2] && next[..2] != snake[2..4] && (1..=8).contains(&next[0]) && (1..=8).contains(&next[1]))
+            .unwrap();
+        assert!((0..4).contains(&agent.step(other, 0, vec![], 2, vec![8, 8, 1, 8], 43)));
+        assert_eq!(agent.replans(), 3);
+    }
+
+    #[test]
+    fn test_risky_food_in_last_round() {
+        // 果子 (3,4) 夹在两个蛇头中间，对手很可能也去吃
+        let me = vec![3, 3, 3, 2, 3, 1, 2, 1];
+        let other = vec![3, 5, 4, 5, 5, 5, 6, 5];
+        let mut agent = SnakeAgent::new(8);
+        assert_eq!(agent.last_risk_level(), -1);
+        assert_ne!(agent.step(me.clone(), 1, other.clone(), 1, vec![3, 4], 30), 0);
+        assert_eq!(agent.last_risk_level(), RiskLevel::Safe.to_code());
+
+        // 最后一回合死了也不亏，值得去抢：对抗搜索排除了向上，风险模型按期望收益改走这一步
+        let mut agent = SnakeAgent::new(8);
+        agent.set_tracing(true);
+        assert_eq!(agent.step(me, 1, other, 1, vec![3, 4], 1), 0);
+        assert_eq!(agent.last_risk_level(), RiskLevel::Likely.to_code());
+        let trace = agent.last_trace().unwrap();
+        assert!(!trace.allowed.contains(&Direction::Up));
+        assert_eq!(trace.branch, Some(Branch::RiskOverride));
+        // 只有吃得到果子的向上能绕过对抗搜索，其余被排除的方向仍然不考虑
+        for candidate in trace.candidates.iter().filter(|c| c.branch == Branch::RiskOverride && !trace.allowed.contains(&c.direction)) {
+            assert_eq!(candidate.rejection.is_none(), candidate.direction == Direction::Up, "{:?}", candidate);
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        // 最近的果子 (1,1) 在死胡同里，对抗搜索只留下向右
+        let me = vec![1, 4, 1, 5, 2, 5, 3, 5];
+        let other = vec![3, 1, 2, 1, 2, 2, 2, 3];
+        let foods = vec![1, 1, 5, 5, 5, 2, 4, 5, 5, 4];
+        let mut agent = SnakeAgent::new(5);
+        assert_eq!(agent.step(me.clone(), 1, other.clone(), 5, foods.clone(), 50), 3);
+        assert!(agent.last_trace().is_none());
+        assert_eq!(agent.last_trace_json(), "");
+
+        agent.set_tracing(true);
+        assert_eq!(agent.step(me, 1, other, 5, foods, 50), 3);
+        let trace = agent.last_trace().unwrap();
+        assert_eq!(trace.allowed, vec![Direction::Right]);
+        assert_eq!(trace.branch, Some(Branch::FoodPath));
+        assert_eq!(trace.target, Some(Position::new(4, 5)));
+        let dead_end = trace.candidates.iter().find(|c| c.target == Position::new(1, 1)).unwrap();
+        assert_eq!((dead_end.branch, dead_end.rejection), (Branch::FoodPath, Some(Rejection::NotAllowed)));
+        assert!(trace.candidates.iter().any(|c| c.branch == Branch::RiskOverride && c.rejection == Some(Rejection::Deadly)));
+        assert!(agent.last_trace_json().contains(r#""branch":"food_path","candidates":[{"branch":"food_path","direction":"down","target":[1,1],"score":3,"rejection":"not_allowed"}"#));
+
+        // 没有果子时跟着蛇尾走，输入不合法时只记下原因
+        agent.step(vec![1, 4, 1, 3, 1, 2, 1, 1], 0, vec![], 0, vec![], 50);
+        let trace = agent.last_trace().unwrap();
+        assert_eq!(trace.branch, Some(Branch::FollowTail));
+        assert!(trace.candidates.iter().filter(|c| c.branch == Branch::NearestFood).all(|c| c.rejection == Some(Rejection::NoFood)));
+        assert_eq!(agent.step(vec![1, 4, 1, 3], 0, vec![], 0, vec![], 50), -2);
+        assert!(agent.last_trace().unwrap().input_error.is_some());
+        assert!(crate::greedy_snake_step_trace(5, vec![1, 4, 1, 3], 0, vec![], 0, vec![], 50).starts_with(r#"{"input_error":"`snake` should have 8 numbers"#));
+    }
+
+    #[test]
+    fn test_reuse_tree() {
+        let mut agent = SnakeAgent::new(5);
+        let me = vec![3, 3, 3, 2, 3, 1, 2, 1];
+        let other = vec![5, 5, 5, 4, 5, 3, 5, 2];
+        let dir = agent.mcts_step(me.clone(), 1, other.clone(), 1, vec![1, 5], 50, 500, f64::INFINITY);
+        let first = agent.tree.as_ref().unwrap().get_visits();
+        assert_eq!(first, 500);
+        agent.mcts_step(moved(&me, dir), 1, moved(&other, 1), 1, vec![1, 5], 49, 100, f64::INFINITY);
+        assert!(agent.tree.as_ref().unwrap().get_visits() > 100);
+        assert_eq!(agent.observed_rounds(), 2);
+    }
+}
@@ -1,231 +1,267 @@
-use astar::astar::Node;
-use map::map::{Direction, Map, Position, Snake};
-use std::collections::{BinaryHeap, HashSet};
+mod agent;
+pub mod trace;
+
+pub use agent::SnakeAgent;
+use snake_core::astar::find_food_paths;
+use snake_core::bitboard::MAX_SNAKES;
+use snake_core::board::{SNAKE_LENGTH, SNAKE_STRIDE};
+use snake_core::input::{check_in_board, check_no_overlap, check_range, parse_positions, parse_snake, InputError};
+use snake_core::map::{Direction, Map, Position, Snake};
+use snake_core::replay::Replay;
+use snake_core::route::{keeps_tail_reachable, stall_move};
+use snake_core::territory::{Owner, Territory};
+use trace::{mark_outscored, note, set_branch, Branch, Candidate, DecisionTrace, Rejection};
 use wasm_bindgen::prelude::*;
 
-pub mod map;
-pub mod astar;
-pub mod random;
+/// 对抗搜索的时间预算（毫秒），README 要求单次决策不超过 500ms，给解析输入和贪心挑选留出余量
+pub const SEARCH_BUDGET_MS: f64 = 200.0;
+/// 迭代加深的最大深度，局面简单时不必把时间用满
+const MAX_SEARCH_DEPTH: i32 = 6;
+/// `mcts_snake_step` 的默认时间预算（毫秒）
+pub const MCTS_BUDGET_MS: f64 = 200.0;
+/// `mcts_snake_step` 的默认迭代次数上限
+const MCTS_ITERATIONS: u32 = 10000;
+/// 剩余回合数超过这个值时还在开局，要求留出两倍蛇长的活动空间
+const CAUTIOUS_ROUNDS: i32 = 70;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
-pub fn greedy_snake_step(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, _round: i32) -> i32 {
-    let (my_snake, other_snakes, foods) = process_input(&input_my_snake, snake_num, &input_other_snakes, food_num, &input_foods);
-    let map = Map::new(my_snake, other_snakes, foods, board_size);
-    let path = find_astar_path(&map);
-    if path.len() > 0 {
-        println!("find astar path :{:#?}", path);
-        match path[0] {
-            Direction::Up => return 0,
-            Direction::Left => return 1,
-            Direction::Down => return 2,
-            Direction::Right => return 3,
-        }
-    } else {
-        // 这里是在严格检查的情况下没有astar路径
-        // 这时的策略是严格检查 但只检查下一步
-        let head = map.get_my_snake().get_head();
-        let mut rec = Direction::Up;
-        let mut min_distance = 114514;
-        for direction in Direction::iter() {
-            let mut pos = *head;
-            match direction {
-                Direction::Up => pos = Position::up(&pos),
-                Direction::Down => pos = Position::down(&pos),
-                Direction::Left => pos = Position::left(&pos),
-                Direction::Right => pos = Position::right(&pos),
-            }
-            if !map.colision_check(&pos) {
-                let distance = calculate_distance(&pos, &map.get_foods());
-                if distance < min_distance {
-                    min_distance = distance;
-                    rec = direction;
-                }
-            }
-        }
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
 
-        if min_distance != 114514 {
-            println!("find eular path");
-            match rec {
-                Direction::Up => return 0,
-                Direction::Left => return 1,
-                Direction::Down => return 2,
-                Direction::Right => return 3,
-            }
-        }
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    date_now()
+}
 
-        println!("this is a dellema");
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    let elapsed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    elapsed.as_secs_f64() * 1000.0
+}
 
-        // 完全没有吃果实路径 这时宽松检查
-        let safe_dirs = Direction::iter()
-            .filter(|dir| {
-                let next_pos = match dir {
-                    Direction::Up => Position::up(head),
-                    Direction::Down => Position::down(head),
-                    Direction::Left => Position::left(head),
-                    Direction::Right => Position::right(head),
-                };
-                !map.strict_obstacles_check(&next_pos)
-            })
-        .collect::<Vec<_>>();
-        if !safe_dirs.is_empty() {
-            let mut min_distance = 32;
-            let mut best_dir = Direction::Up;
-            for dir in safe_dirs {
-                let next_pos = match dir {
-                    Direction::Up => Position::up(head),
-                    Direction::Down => Position::down(head),
-                    Direction::Left => Position::left(head),
-                    Direction::Right => Position::right(head),
-                };
-                // TODO: 这里的距离需要调整
-                // 距离棋盘中心最近
-                let distance = ((board_size / 2) - next_pos.get_x()).abs() + ((board_size / 2) - next_pos.get_y()).abs();
-                if distance < min_distance {
-                    min_distance = distance;
-                    best_dir = dir;
-                }
-            }
-            match best_dir {
-                Direction::Up => return 0,
-                Direction::Left => return 1,
-                Direction::Down => return 2,
-                Direction::Right => return 3,
-            }
+/// T3 的决策函数，返回方向编号；输入不合法时返回 `InputError::code` 给出的负数
+///
+/// 每次调用都从头决策，要跨回合沿用路线和搜索树时使用 `SnakeAgent`
+#[wasm_bindgen]
+pub fn greedy_snake_step(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> i32 {
+    SnakeAgent::new(board_size).step(input_my_snake, snake_num, input_other_snakes, food_num, input_foods, round)
+}
+
+/// 与 `greedy_snake_step` 参数相同，返回这一步所依据的完整路线
+///
+/// 返回 `[步数, 目标 x, 目标 y, 每一步的方向编号...]`，第一步就是 `greedy_snake_step` 的结果。
+/// 朝果子走时目标是那个果子，否则路线只有一步，目标是走完这一步后蛇头的位置。
+/// 对手会移动、果子会刷新，后面的步骤只是当前局面下的打算；输入不合法时返回只有一个错误码的数组
+#[wasm_bindgen]
+pub fn greedy_snake_step_path(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> Vec<i32> {
+    SnakeAgent::new(board_size).step_path(input_my_snake, snake_num, input_other_snakes, food_num, input_foods, round)
+}
+
+/// 与 `greedy_snake_step` 相同的决策，返回决策过程的记录（格式见 `trace::DecisionTrace::to_json`）
+#[wasm_bindgen]
+pub fn greedy_snake_step_trace(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> String {
+    let mut agent = SnakeAgent::new(board_size);
+    agent.set_tracing(true);
+    agent.step(input_my_snake, snake_num, input_other_snakes, food_num, input_foods, round);
+    agent.last_trace_json()
+}
+
+/// 与 `greedy_snake_step` 参数相同的 MCTS 策略，可以在 `game-config.js` 中替换使用
+#[wasm_bindgen]
+pub fn mcts_snake_step(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32) -> i32 {
+    mcts_snake_step_with_budget(board_size, input_my_snake, snake_num, input_other_snakes, food_num, input_foods, round, MCTS_ITERATIONS, MCTS_BUDGET_MS)
+}
+
+/// 可以指定预算的 `mcts_snake_step`，迭代次数和时间任一用完就停止
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn mcts_snake_step_with_budget(board_size: i32, input_my_snake: Vec<i32>, snake_num: i32, input_other_snakes: Vec<i32>, food_num: i32, input_foods: Vec<i32>, round: i32, max_iterations: u32, max_ms: f64) -> i32 {
+    SnakeAgent::new(board_size).mcts_step(input_my_snake, snake_num, input_other_snakes, food_num, input_foods, round, max_iterations, max_ms)
+}
+
+/// 把二进制回放（见 `snake_core::replay`）转成 JSON
+///
+/// 数据不合法时返回 `ReplayError` 的说明，在 JS 中作为异常抛出
+#[wasm_bindgen]
+pub fn replay_to_json(bytes: Vec<u8>) -> Result<String, String> {
+    Replay::from_bytes(&bytes).map(|replay| replay.to_json()).map_err(|error| error.to_string())
+}
+
+/// 把 JSON 回放转成二进制，数据不合法时与 `replay_to_json` 一样返回错误说明
+#[wasm_bindgen]
+pub fn replay_to_binary(json: String) -> Result<Vec<u8>, String> {
+    Replay::from_json(&json).map(|replay| replay.to_bytes()).map_err(|error| error.to_string())
+}
+
+/// 只在 `allowed` 中挑选方向的贪心策略，返回路线的目标和路线
+///
+/// `rounds_left` 是包括这一回合在内还剩的回合数，剩下的回合走不到的果子不去。
+/// `trace` 为 `Some` 时记下走的分支和考虑过的每个方向
+fn greedy_plan(map: &Map, allowed: &[Direction], rounds_left: i32, trace: &mut Option<DecisionTrace>) -> (Position, Vec<Direction>) {
+    let head = *map.get_my_snake().get_head();
+    let board_size = map.get_size();
+    let enough_space = |direction: Direction| keeps_space(map, direction, rounds_left);
+    let step = |direction: Direction| (head.step(direction), vec![direction]);
+
+    // 对手能先到的果子去了也是白跑，同时到达还会头对头；先去自己控制的果子，再去争夺中的，最后才是对手的
+    let territory = Territory::compute(map);
+    let rank = |food: &Position| match territory.owner(food) {
+        Owner::Snake(0) => 0,
+        Owner::Contested => 1,
+        _ => 2,
+    };
+    let target_of = |path: &[Direction]| path.iter().fold(head, |pos, direction| pos.step(*direction));
+    let mut paths = find_food_paths(map);
+    paths.sort_by_key(|path| (path.len() as i32 > rounds_left, rank(&target_of(path))));
+
+    // 同一档里从近到远尝试每个果子，跳过第一步就会把自己困住的路径
+    for path in paths {
+        let rejection = if path.len() as i32 > rounds_left {
+            Some(Rejection::OutOfTime)
+        } else if !allowed.contains(&path[0]) {
+            Some(Rejection::NotAllowed)
+        } else if !enough_space(path[0]) {
+            Some(Rejection::NoSpace)
         } else {
-            return 0;
+            None
+        };
+        note(trace, || Candidate { branch: Branch::FoodPath, direction: path[0], target: target_of(&path), score: path.len() as f64, rejection });
+        if rejection.is_none() {
+            set_branch(trace, Branch::FoodPath);
+            return (target_of(&path), path);
         }
     }
-}
+    let in_time: Vec<Position> = map.get_foods().iter().copied().filter(|food| head.manhattan(food) <= rounds_left).collect();
+    let mut preferred_foods: Vec<Position> = in_time.iter().copied().filter(|food| rank(food) == 0).collect();
+    if preferred_foods.is_empty() {
+        preferred_foods = in_time;
+    }
 
-fn calculate_distance(pos1: &Position, foods: &Vec<Position>) -> i32 {
-    let mut min_distance = i32::MAX;
-    for food in foods {
-        let distance = (pos1.get_x() - food.get_x()).abs() + (pos1.get_y() - food.get_y()).abs();
-        if distance < min_distance {
+    // 这里是在严格检查的情况下没有astar路径
+    // 这时的策略是严格检查 但只检查下一步，并且走完之后还要能走到自己的蛇尾，快结束时不必
+    let tail_open = |direction: Direction| rounds_left <= map.get_my_snake().get_body().len() as i32 || keeps_tail_reachable(map, direction);
+    let mut rec = Direction::Up;
+    let mut min_distance = 114514;
+    for direction in allowed.iter().copied() {
+        let pos = head.step(direction);
+        let rejection = if map.colision_check(&pos) {
+            Some(Rejection::Collision)
+        } else if !enough_space(direction) {
+            Some(Rejection::NoSpace)
+        } else if !tail_open(direction) {
+            Some(Rejection::TailUnreachable)
+        } else if preferred_foods.is_empty() {
+            Some(Rejection::NoFood)
+        } else {
+            None
+        };
+        let distance = calculate_distance(&pos, &preferred_foods);
+        note(trace, || Candidate { branch: Branch::NearestFood, direction, target: pos, score: distance as f64, rejection });
+        if rejection.is_none() && distance < min_distance {
             min_distance = distance;
+            rec = direction;
         }
     }
-    min_distance
-}
 
-fn process_input(input_my_snake: &Vec<i32>, snake_num: i32, input_other_snakes: &Vec<i32>, food_num: i32, input_foods: &Vec<i32>) -> (Snake, Vec<Snake>, Vec<Position>){
-    let mut my_snake = Snake::new();
-    let mut i:usize = 0;
-    while i < 8 {
-        my_snake.append_body(Position::new(input_my_snake[i], input_my_snake[i + 1]));
-        i += 2;
+    if min_distance != 114514 {
+        mark_outscored(trace, Branch::NearestFood, rec);
+        set_branch(trace, Branch::NearestFood);
+        return step(rec);
     }
 
-    let mut _j:usize = 0;
-    let mut other_snakes:Vec<Snake> = Vec::new();
-    for i in 0..snake_num {
-        let mut snake = Snake::new();
-        _j = 0;
-        while _j < 8 {
-            let pos = Position::new(input_other_snakes[8 * i as usize + _j], input_other_snakes[8 * i as usize + _j + 1]);
-            snake.append_body(pos);
-            _j += 2;
+    // 完全没有吃果实路径 跟着蛇尾兜圈子，等果子能走到了再去
+    let safe_dirs = allowed.iter()
+        .copied()
+        .filter(|dir| !map.strict_obstacles_check(&head.step(*dir)))
+        .collect::<Vec<_>>();
+    if let Some(direction) = stall_move(map, &safe_dirs) {
+        for candidate in allowed.iter().copied() {
+            let pos = head.step(candidate);
+            note(trace, || {
+                let rejection = if !safe_dirs.contains(&candidate) {
+                    Some(Rejection::Collision)
+                } else if !keeps_tail_reachable(map, candidate) {
+                    Some(Rejection::TailUnreachable)
+                } else {
+                    None
+                };
+                Candidate { branch: Branch::FollowTail, direction: candidate, target: pos, score: map.reachable_area(&pos, 1, usize::MAX) as f64, rejection }
+            });
         }
-        other_snakes.push(snake);
-    }
-
-    i = 0;
-    let mut foods:Vec<Position> = Vec::new();
-    while i < input_foods.len() as usize {
-        let food = Position::new(input_foods[i], input_foods[i + 1]);
-        foods.push(food);
-        i += 2;
+        mark_outscored(trace, Branch::FollowTail, direction);
+        set_branch(trace, Branch::FollowTail);
+        return step(direction);
     }
 
-    if other_snakes.len() != snake_num as usize {
-        panic!("other_snakes len is not equal to snake_num, what the hell!");
+    // 连蛇尾都走不到了 这时宽松检查
+    // 优先选活动空间大的方向，空间一样大时选走完之后控制的格子多的，再一样时选离棋盘中心近的
+    let mut best_key = (0, 0, i32::MIN);
+    let mut best_dir = allowed.first().copied().unwrap_or(Direction::Up);
+    for dir in allowed.iter().copied().filter(|dir| !safe_dirs.contains(dir)) {
+        note(trace, || Candidate { branch: Branch::Dilemma, direction: dir, target: head.step(dir), score: 0.0, rejection: Some(Rejection::Collision) });
     }
-    if foods.len() != food_num as usize {
-        panic!("foods len is not equal to food_num, what the hell!");
+    for dir in safe_dirs {
+        let next_pos = head.step(dir);
+        let area = map.reachable_area(&next_pos, 1, usize::MAX);
+        // TODO: 这里的距离需要调整
+        // 距离棋盘中心最近
+        let distance = ((board_size / 2) - next_pos.get_x()).abs() + ((board_size / 2) - next_pos.get_y()).abs();
+        let after = Map::with_head_risks(map.get_my_snake().moved(dir), map.get_other_snakes().clone(), map.get_foods().clone(), board_size, &[]);
+        let controlled = Territory::compute(&after).area(0);
+        note(trace, || Candidate { branch: Branch::Dilemma, direction: dir, target: next_pos, score: area as f64, rejection: None });
+        if (area, controlled, -distance) > best_key {
+            best_key = (area, controlled, -distance);
+            best_dir = dir;
+        }
     }
-    (my_snake, other_snakes, foods)
+    mark_outscored(trace, Branch::Dilemma, best_dir);
+    set_branch(trace, Branch::Dilemma);
+    step(best_dir)
 }
 
-fn find_astar_path(map: &Map) -> Vec<Direction> {
-    let foods = map.get_foods();
+/// 走完这一步后至少要留出 `space_needed` 个格子的活动空间，否则就是钻进了死胡同
+fn keeps_space(map: &Map, direction: Direction, rounds_left: i32) -> bool {
+    let threshold = space_needed(map.get_my_snake().get_body().len(), rounds_left);
+    map.reachable_area(&map.get_my_snake().get_head().step(direction), 1, threshold) >= threshold
+}
 
-    let head = *map.get_my_snake().get_head();
-    // 当前是选择最近的果子作为目标
-    let mut shortest_path:Vec<Direction> = Vec::new();
-    for food in foods {
-        let mut _tmp_path:Vec<Direction>  = Vec::new();
-        let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
-        let mut closed_set: HashSet<Position> = HashSet::new();
-        let node = Node::new((head).clone(), 0, Node::heuristic(&head, &food), None);
-        open_set.push(node);
-        while !open_set.is_empty() {
-            let cur = match open_set.pop() {
-                Some(pos) => pos,
-                None => panic!("Got a None Node!"),
-            };
-            if cur.get_pos() == food {
-                _tmp_path = reconstruct_path(&cur);
-                println!("find astar path :{:#?}", _tmp_path);
-                if shortest_path.is_empty() || shortest_path.len() > _tmp_path.len() {
-                    shortest_path = _tmp_path;
-                }
-                break;
-            }
-    
-            closed_set.insert((*cur.get_pos()).clone());
-    
-            for direction in Direction::iter() {
-                let mut _neighbor = Position::new(-1, -1);
-                match direction {
-                    Direction::Up => _neighbor = Position::up(cur.get_pos()),
-                    Direction::Down => _neighbor = Position::down(cur.get_pos()),
-                    Direction::Left => _neighbor = Position::left(cur.get_pos()),
-                    Direction::Right => _neighbor = Position::right(cur.get_pos()),
-                }
-                if (!closed_set.contains(&_neighbor)) && (!map.colision_check(&_neighbor)){
-                    let g = cur.get_g() + 1;
-                    let h = Node::heuristic(&_neighbor, food);
-                    open_set.push(Node::new((_neighbor).clone(), g, h, Some((cur).clone())));
-                }
-            }
-        }
+/// 一般要留出一条蛇长的空间；开局时更保守，留两倍；
+/// 快结束时只要撑过剩下的回合，最后一回合之后活着也没有用
+fn space_needed(length: usize, rounds_left: i32) -> usize {
+    if rounds_left > CAUTIOUS_ROUNDS {
+        2 * length
+    } else {
+        length.min(rounds_left.max(0) as usize)
     }
+}
 
-    return shortest_path
-
+fn calculate_distance(pos1: &Position, foods: &[Position]) -> i32 {
+    foods.iter().map(|food| pos1.manhattan(food)).min().unwrap_or(i32::MAX)
 }
 
-fn reconstruct_path(node: &Node) -> Vec<Direction> {
-    let mut path: Vec<Direction> = Vec::new();
-    let mut current_node = Some(node);
-
-    while let Some(node) = current_node {
-        if let Some(parent) = node.get_parent() {
-            let from = parent.get_pos(); 
-            let to = node.get_pos();
-            let mut _neighbor = Position::new(-1, -1);
-
-            for direction in Direction::iter() {
-                match direction {
-                    Direction::Up => _neighbor = Position::up(from),
-                    Direction::Down => _neighbor = Position::down(from),
-                    Direction::Left => _neighbor = Position::left(from),
-                    Direction::Right => _neighbor = Position::right(from),
-                }
-                if &_neighbor == to {
-                    path.push(direction);
-                    break;
-                }
-            }
-
-            current_node = Some(parent); 
-        } else {
-            break;
-        }
+/// 解析并检查输入，不合法时返回 `InputError`，由导出函数转成错误码
+fn process_input(board_size: i32, input_my_snake: &[i32], snake_num: i32, input_other_snakes: &[i32], food_num: i32, input_foods: &[i32]) -> Result<(Snake, Vec<Snake>, Vec<Position>), InputError> {
+    check_range("board_size", board_size, 1, i32::MAX)?;
+    check_range("snake_num", snake_num, 0, MAX_SNAKES as i32 - 1)?;
+    check_range("food_num", food_num, 0, board_size.saturating_mul(board_size))?;
+
+    let my_snake = parse_snake("snake", input_my_snake, Some(SNAKE_LENGTH), board_size)?;
+    let expected = snake_num as usize * SNAKE_STRIDE;
+    if input_other_snakes.len() != expected {
+        return Err(InputError::WrongLength { field: "other_snakes", expected, actual: input_other_snakes.len() });
     }
+    let other_snakes = input_other_snakes.chunks(SNAKE_STRIDE)
+        .map(|chunk| parse_snake("other_snakes", chunk, Some(SNAKE_LENGTH), board_size))
+        .collect::<Result<Vec<Snake>, InputError>>()?;
+    let foods = parse_positions("foods", input_foods, Some(food_num as usize))?;
+    check_in_board("foods", &foods, board_size)?;
 
-    path.reverse();
-    path
+    let bodies = std::iter::once(my_snake.get_body()).chain(other_snakes.iter().map(Snake::get_body));
+    check_no_overlap(bodies.chain(std::iter::once(&foods[..])))?;
+    Ok((my_snake, other_snakes, foods))
 }
 
 
@@ -243,7 +279,13 @@ mod test {
     //     ]
     //   },
 
-    use crate::greedy_snake_step;
+    use crate::{greedy_plan, greedy_snake_step, greedy_snake_step_path, mcts_snake_step, replay_to_binary, replay_to_json, space_needed};
+    use snake_core::map::{Direction, Map, Position, Snake};
+    use snake_core::render::{render, RenderOptions};
+
+    fn snake(coords: &[i32]) -> Snake {
+        Snake::from_body(coords.chunks(2).map(|pair| Position::new(pair[0], pair[1])).collect())
+    }
 
     // board_size: i32
     // input_my_snake: Vec<i32>
@@ -270,4 +312,123 @@ mod test {
             _ => println!("Invalid Return"),
         }
     }
+
+    #[test]
+    fn test_bad_input() {
+        let my_snake = vec![1, 4, 1, 3, 1, 2, 1, 1];
+        let other = vec![5, 2, 5, 3, 5, 4, 5, 5];
+        let foods = vec![3, 3, 2, 4];
+        // snake_num 与数组长度不符
+        assert_eq!(greedy_snake_step(5, my_snake.clone(), 2, other.clone(), 2, foods.clone(), 50), -2);
+        assert_eq!(greedy_snake_step(5, my_snake.clone(), 1, other.clone(), 3, foods.clone(), 50), -2);
+        // 果子在蛇身上
+        assert_eq!(greedy_snake_step(5, my_snake.clone(), 1, other.clone(), 1, vec![5, 5], 50), -5);
+        // 两条蛇重叠
+        assert_eq!(mcts_snake_step(5, my_snake.clone(), 1, my_snake.clone(), 2, foods.clone(), 50), -5);
+        assert_eq!(greedy_snake_step(5, vec![1, 4, 1, 3, 1, 2, 2, 1], 1, other.clone(), 2, foods.clone(), 50), -3);
+        // 已经死亡的蛇
+        assert_eq!(greedy_snake_step(5, vec![-1; 8], 1, other.clone(), 2, foods.clone(), 50), -4);
+        assert_eq!(greedy_snake_step(0, my_snake, 1, other, 2, foods, 50), -6);
+    }
+
+    #[test]
+    fn test_board_larger_than_bitboard() {
+        // 20×20 放不进位棋盘，跳过对抗搜索和 MCTS，仍然按贪心规则朝果子走
+        let my_snake = vec![10, 10, 10, 9, 10, 8, 10, 7];
+        let other = vec![1, 4, 1, 3, 1, 2, 1, 1];
+        let foods = vec![10, 15];
+        assert_eq!(greedy_snake_step(20, my_snake.clone(), 1, other.clone(), 1, foods.clone(), 50), 0);
+        assert_eq!(mcts_snake_step(20, my_snake.clone(), 1, other.clone(), 1, foods.clone(), 50), 0);
+        let path = greedy_snake_step_path(20, my_snake, 1, other, 1, foods, 50);
+        assert_eq!(path, vec![5, 10, 15, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_avoid_dead_end() {
+        // 最近的果子 (1,1) 在对手身体和边界围成的死胡同里，钻进去就出不来
+        let board_size = 5;
+        let input_my_snake = vec![1, 4, 1, 5, 2, 5, 3, 5];
+        let input_other_snakes = vec![3, 1, 2, 1, 2, 2, 2, 3];
+        let input_foods = vec![1, 1, 5, 5, 5, 2, 4, 5, 5, 4];
+        let dir = greedy_snake_step(board_size, input_my_snake, 1, input_other_snakes, 5, input_foods, 50);
+        assert_eq!(dir, 3);
+    }
+
+    #[test]
+    fn test_skip_food_opponent_reaches_first() {
+        // (4,5) 和 (7,2) 离我方蛇头都是 3 步，但对手蛇头 (4,7) 离 (4,5) 只有 2 步
+        let me = snake(&[4, 2, 4, 1, 3, 1, 2, 1]);
+        let other = snake(&[4, 7, 4, 8, 5, 8, 6, 8]);
+        let map = Map::new(me, vec![other], vec![Position::new(4, 5), Position::new(7, 2)], 8);
+        let all: Vec<Direction> = Direction::iter().collect();
+        let (target, path) = greedy_plan(&map, &all, 50, &mut None);
+        let board = render(&map, &RenderOptions { path: path.clone(), ..Default::default() });
+        assert_eq!(target, Position::new(7, 2), "\n{board}");
+        assert_eq!(path.len(), 3, "\n{board}");
+    }
+
+    #[test]
+    fn test_endgame() {
+        // 左边 (1,3) 的果子在只有 3 格的死胡同口
+        let my_snake = vec![1, 4, 1, 5, 2, 5, 3, 5];
+        let other = vec![3, 1, 2, 1, 2, 2, 2, 3];
+        let foods = vec![1, 3, 5, 5];
+        assert_eq!(greedy_snake_step(5, my_snake.clone(), 1, other.clone(), 2, foods.clone(), 50), 3);
+        // 只剩 3 回合时，3 格足够撑到结束
+        assert_eq!(greedy_snake_step(5, my_snake.clone(), 1, other.clone(), 2, foods.clone(), 3), 2);
+        assert_eq!(greedy_snake_step(5, my_snake, 1, other, 2, foods, 1), 2);
+
+        assert_eq!(space_needed(4, 90), 8);
+        assert_eq!(space_needed(4, 50), 4);
+        assert_eq!(space_needed(4, 2), 2);
+    }
+
+    #[test]
+    fn test_stall_without_food() {
+        // 场上没有果子，只能跟着蛇尾兜圈子，60 回合都不能死
+        let mut snake = vec![1, 4, 1, 3, 1, 2, 1, 1];
+        for round in (1..=60).rev() {
+            let dir = greedy_snake_step(5, snake.clone(), 0, vec![], 0, vec![], round);
+            let head = Position::new(snake[0], snake[1]).step(Direction::from_code(dir).unwrap());
+            assert!((1..=5).contains(&head.get_x()) && (1..=5).contains(&head.get_y()), "撞墙了");
+            assert!(!snake[..6].chunks(2).any(|body| body == [head.get_x(), head.get_y()]), "撞到了自己");
+            snake.rotate_right(2);
+            snake[0..2].copy_from_slice(&[head.get_x(), head.get_y()]);
+        }
+    }
+
+    #[test]
+    fn test_refuse_food_out_of_time() {
+        // (8,8) 的果子要走 11 步
+        let map = Map::new(snake(&[1, 4, 1, 3, 1, 2, 1, 1]), vec![], vec![Position::new(8, 8)], 8);
+        let all: Vec<Direction> = Direction::iter().collect();
+        let (target, path) = greedy_plan(&map, &all, 50, &mut None);
+        assert_eq!((target, path.len()), (Position::new(8, 8), 11));
+        let (target, path) = greedy_plan(&map, &all, 10, &mut None);
+        assert_eq!(path.len(), 1);
+        assert_ne!(target, Position::new(8, 8));
+    }
+
+    #[test]
+    fn test_replay_conversion() {
+        let json = r#"{"format":"snake-replay","version":1,"board_size":5,"seed":"7","max_rounds":50,"food_count":1,"strategies":["greedy"],"initial_snakes":[[1,4,1,3,1,2,1,1]],"initial_foods":[3,3],"turns":[{"moves":[{"seat":0,"action":3,"ms":0.5}],"deaths":[],"food_spawns":[]}]}"#;
+        let bytes = replay_to_binary(json.to_string()).unwrap();
+        assert!(bytes.len() < json.len());
+        assert_eq!(replay_to_json(bytes).unwrap(), json);
+        assert_eq!(replay_to_binary("{}".to_string()), Err("not a snake replay".to_string()));
+        assert_eq!(replay_to_json(vec![1, 2, 3]), Err("not a snake replay".to_string()));
+    }
+
+    #[test]
+    fn test_full_path() {
+        // 右边 (2,3) 有果子，路线只有一步
+        let my_snake = vec![1, 3, 1, 2, 1, 1, 2, 1];
+        let path = greedy_snake_step_path(5, my_snake.clone(), 0, vec![], 2, vec![2, 3, 5, 5], 50);
+        assert_eq!(path, vec![1, 2, 3, 3]);
+        let path = greedy_snake_step_path(5, my_snake.clone(), 0, vec![], 1, vec![5, 5], 50);
+        assert_eq!(path[1..3], [5, 5]);
+        assert_eq!(path[0] as usize, path.len() - 3);
+        assert_eq!(path[3], greedy_snake_step(5, my_snake.clone(), 0, vec![], 1, vec![5, 5], 50));
+        assert_eq!(greedy_snake_step_path(5, my_snake, 0, vec![], 1, vec![1, 3], 50), vec![-5]);
+    }
 }
@@ -0,0 +1,186 @@
+//! T3 决策过程的记录
+//!
+//! 以前决策过程只靠 `println!` 打印，在 wasm 中看不到。`SnakeAgent::set_tracing` 打开之后，每次决策把对抗搜索的结果、
+//! 走的是哪个分支、考虑过的每个候选方向和它的分数、被排除的原因以及最后的目标记在 `DecisionTrace` 里：
+//! 本地用 `SnakeAgent::last_trace` 取，wasm 中用 `SnakeAgent::last_trace_json` 或 `greedy_snake_step_trace` 取 JSON。
+//! 记录都通过 `note` 传入闭包，没打开时只多一次 `Option` 判断，不计算分数也不分配内存。
+
+use snake_core::input::InputError;
+use snake_core::json::Json;
+use snake_core::map::{Direction, Position};
+use snake_core::risk::RiskLevel;
+
+/// 决策走的分支
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Branch {
+    /// 沿用上一回合的路线
+    ReusePlan,
+    /// 沿 A* 路线去某个果子
+    FoodPath,
+    /// 没有 A* 路线，往最近的果子方向走一步
+    NearestFood,
+    /// 没有能去的果子，跟着蛇尾兜圈子
+    FollowTail,
+    /// 连蛇尾都走不到，选活动空间最大的方向
+    Dilemma,
+    /// 按头对头风险的期望收益改走另一步
+    RiskOverride,
+    Mcts,
+}
+
+impl Branch {
+    pub fn name(self) -> &'static str {
+        match self {
+            Branch::ReusePlan => "reuse_plan",
+            Branch::FoodPath => "food_path",
+            Branch::NearestFood => "nearest_food",
+            Branch::FollowTail => "follow_tail",
+            Branch::Dilemma => "dilemma",
+            Branch::RiskOverride => "risk_override",
+            Branch::Mcts => "mcts",
+        }
+    }
+}
+
+/// 候选方向被排除的原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// 对抗搜索认为最坏情况下会吃亏
+    NotAllowed,
+    /// 剩下的回合走不到
+    OutOfTime,
+    /// 走完之后活动空间不够，见 `keeps_space`
+    NoSpace,
+    /// 会撞上障碍物或蛇身
+    Collision,
+    /// 走完之后走不到自己的蛇尾
+    TailUnreachable,
+    /// 剩下的回合里没有够得着的果子
+    NoFood,
+    /// 头对头必死，见 `RiskLevel::Deadly`
+    Deadly,
+    /// 能走，但分数不如选中的方向
+    Outscored,
+}
+
+impl Rejection {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rejection::NotAllowed => "not_allowed",
+            Rejection::OutOfTime => "out_of_time",
+            Rejection::NoSpace => "no_space",
+            Rejection::Collision => "collision",
+            Rejection::TailUnreachable => "tail_unreachable",
+            Rejection::NoFood => "no_food",
+            Rejection::Deadly => "deadly",
+            Rejection::Outscored => "outscored",
+        }
+    }
+}
+
+/// 某个分支考虑过的一个方向
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    pub branch: Branch,
+    pub direction: Direction,
+    /// 朝果子走时是那个果子，否则是走完这一步后蛇头的位置
+    pub target: Position,
+    /// 分数的含义随分支而定：`FoodPath` 为路线长度，`NearestFood` 为到果子的距离（都是越小越好），
+    /// `FollowTail` 和 `Dilemma` 为活动空间，`RiskOverride` 为期望收益，`Mcts` 为访问次数
+    pub score: f64,
+    /// 被选中或者还没来得及比较时为 `None`
+    pub rejection: Option<Rejection>,
+}
+
+/// 一次决策的完整记录
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecisionTrace {
+    /// 输入不合法时只有这一项
+    pub input_error: Option<InputError>,
+    /// 对抗搜索完整搜完的深度和我方每个方向的估值
+    pub search_depth: i32,
+    pub search_values: Vec<(Direction, i32)>,
+    /// 对抗搜索留下的方向
+    pub allowed: Vec<Direction>,
+    pub branch: Option<Branch>,
+    pub candidates: Vec<Candidate>,
+    /// 最后的路线，第一步就是这一回合的方向
+    pub path: Vec<Direction>,
+    pub target: Option<Position>,
+    /// 选中的方向的头对头风险
+    pub risk: Option<RiskLevel>,
+}
+
+/// 打开记录时把 `candidate()` 记下来
+pub(crate) fn note(trace: &mut Option<DecisionTrace>, candidate: impl FnOnce() -> Candidate) {
+    if let Some(trace) = trace {
+        trace.candidates.push(candidate());
+    }
+}
+
+pub(crate) fn set_branch(trace: &mut Option<DecisionTrace>, branch: Branch) {
+    if let Some(trace) = trace {
+        trace.branch = Some(branch);
+    }
+}
+
+/// `branch` 分支中没有被排除、又不是 `chosen` 的候选方向，都是分数比不过
+pub(crate) fn mark_outscored(trace: &mut Option<DecisionTrace>, branch: Branch, chosen: Direction) {
+    if let Some(trace) = trace {
+        for candidate in trace.candidates.iter_mut().filter(|c| c.branch == branch && c.direction != chosen && c.rejection.is_none()) {
+            candidate.rejection = Some(Rejection::Outscored);
+        }
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Left => "left",
+        Direction::Down => "down",
+        Direction::Right => "right",
+    }
+}
+
+fn risk_name(level: RiskLevel) -> &'static str {
+    match level {
+        RiskLevel::Safe => "safe",
+        RiskLevel::Possible => "possible",
+        RiskLevel::Likely => "likely",
+        RiskLevel::Deadly => "deadly",
+    }
+}
+
+impl DecisionTrace {
+    /// 没有的字段写成 `null`，坐标写成 `[x, y]`
+    pub fn to_json(&self) -> String {
+        let text = |s: &str| Json::String(s.to_string());
+        let position = |pos: &Position| Json::Array(vec![Json::Number(pos.get_x() as f64), Json::Number(pos.get_y() as f64)]);
+        let optional = |value: Option<Json>| value.unwrap_or(Json::Null);
+        let candidates = self.candidates.iter()
+            .map(|c| Json::Object(vec![
+                ("branch".to_string(), text(c.branch.name())),
+                ("direction".to_string(), text(direction_name(c.direction))),
+                ("target".to_string(), position(&c.target)),
+                ("score".to_string(), Json::Number(c.score)),
+                ("rejection".to_string(), optional(c.rejection.map(|r| text(r.name())))),
+            ]))
+            .collect();
+        let root = Json::Object(vec![
+            ("input_error".to_string(), optional(self.input_error.as_ref().map(|error| text(&error.to_string())))),
+            ("search_depth".to_string(), Json::Number(self.search_depth as f64)),
+            ("search_values".to_string(), Json::Array(self.search_values.iter()
+                .map(|(direction, value)| Json::Array(vec![text(direction_name(*direction)), Json::Number(*value as f64)]))
+                .collect())),
+            ("allowed".to_string(), Json::Array(self.allowed.iter().map(|d| text(direction_name(*d))).collect())),
+            ("branch".to_string(), optional(self.branch.map(|b| text(b.name())))),
+            ("candidates".to_string(), Json::Array(candidates)),
+            ("path".to_string(), Json::Array(self.path.iter().map(|d| text(direction_name(*d))).collect())),
+            ("target".to_string(), optional(self.target.as_ref().map(position))),
+            ("risk".to_string(), optional(self.risk.map(|level| text(risk_name(level))))),
+        ]);
+        let mut out = String::new();
+        root.write(&mut out);
+        out
+    }
+}
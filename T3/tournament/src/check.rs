@@ -0,0 +1,111 @@
+//! `--check-replay`：用当前的规则和策略重新跑一遍记录下来的对局
+//!
+//! 先按规则逐回合核对死亡和补充的果子，报告第一处分歧；再把每一回合的局面交给座位上记录的策略
+//! （只限 `registry` 里有的），列出现在会做出不同选择的回合。超时的回合记录的是代替的动作，不参与比较。
+//! 有任何不一致时返回非零的退出码，输掉的对局可以直接当作回归测试。
+
+use crate::strategies::{Entry, Strategy};
+use snake_core::game::TIME_LIMIT_MS;
+use snake_core::map::Direction;
+use snake_core::notation::Board;
+use snake_core::replay::{Replay, BINARY_MAGIC};
+use std::path::Path;
+
+/// 一个座位上重新运行的策略
+struct Rerun {
+    seat: usize,
+    name: String,
+    player: Box<dyn Strategy>,
+    decisions: usize,
+    timeouts: usize,
+    /// (回合, 记录的动作, 现在的动作)
+    changed: Vec<(i32, i32, i32)>,
+}
+
+/// 检查 `path` 中的回放，返回进程的退出码
+pub fn run(path: &Path, entries: &[Entry]) -> i32 {
+    let replay = match read(path) {
+        Ok(replay) => replay,
+        Err(message) => {
+            eprintln!("cannot read replay {}: {message}", path.display());
+            return 2;
+        }
+    };
+    println!("replay {}: board {}x{}, {} snakes, {} rounds, {} turns recorded, seed {}",
+        path.display(), replay.board_size, replay.board_size, replay.initial_snakes.len(), replay.max_rounds, replay.turns.len(), replay.seed);
+
+    // 与 `play_game` 一样，座位 seat 上的策略用 seed + seat 初始化
+    let mut reruns: Vec<Rerun> = replay.strategies.iter().enumerate()
+        .filter_map(|(seat, name)| {
+            let entry = entries.iter().find(|entry| entry.name == name)?;
+            Some(Rerun { seat, name: name.clone(), player: (entry.build)(replay.seed.wrapping_add(seat as u64)), decisions: 0, timeouts: 0, changed: vec![] })
+        })
+        .collect();
+
+    // 最近一次结算之前的局面，出现分歧时打印出来
+    let mut position = String::new();
+    let result = replay.resimulate(|state, turn| {
+        position = Board::from_game(state).to_compact();
+        for rerun in &mut reruns {
+            let Some(recorded) = turn.moves.iter().find(|m| m.seat == rerun.seat) else {
+                continue;
+            };
+            let obs = state.observation(rerun.seat).expect("seat has a recorded move");
+            // 超时的回合也要交给策略，有状态的策略才能跟上对局
+            let action = rerun.player.step(&obs);
+            if recorded.decision_ms > TIME_LIMIT_MS {
+                rerun.timeouts += 1;
+                continue;
+            }
+            rerun.decisions += 1;
+            if action != recorded.action {
+                rerun.changed.push((state.get_round() + 1, recorded.action, action));
+            }
+        }
+    });
+
+    let mut ok = true;
+    match &result {
+        Ok(state) => println!("rules: all {} turns match, final scores {:?}", replay.turns.len(), state.get_scores()),
+        Err(divergence) => {
+            ok = false;
+            println!("rules: first divergence at {divergence}");
+            if divergence.round() > 0 {
+                println!("  position before round {}: {position}", divergence.round());
+            }
+        }
+    }
+    for rerun in &reruns {
+        println!("seat {} ({}): {} of {} decisions changed{}", rerun.seat, rerun.name, rerun.changed.len(), rerun.decisions,
+            if rerun.timeouts > 0 { format!(", {} timeouts skipped", rerun.timeouts) } else { String::new() });
+        for (round, recorded, action) in &rerun.changed {
+            println!("  round {round}: recorded {}, now {}", direction_name(*recorded), direction_name(*action));
+        }
+        ok &= rerun.changed.is_empty();
+    }
+    if reruns.is_empty() {
+        println!("no recorded strategy is registered, decisions are not checked");
+    }
+    if ok { 0 } else { 1 }
+}
+
+/// 按开头的魔数区分二进制和 JSON 格式
+pub fn read(path: &Path) -> Result<Replay, String> {
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    if bytes.starts_with(&BINARY_MAGIC) {
+        Replay::from_bytes(&bytes).map_err(|error| error.to_string())
+    } else {
+        let text = String::from_utf8(bytes).map_err(|_| "not UTF-8 text".to_string())?;
+        Replay::from_json(&text).map_err(|error| error.to_string())
+    }
+}
+
+pub fn direction_name(code: i32) -> String {
+    match Direction::from_code(code) {
+        Some(Direction::Up) => "up".to_string(),
+        Some(Direction::Left) => "left".to_string(),
+        Some(Direction::Down) => "down".to_string(),
+        Some(Direction::Right) => "right".to_string(),
+        None => format!("invalid action {code}"),
+    }
+}
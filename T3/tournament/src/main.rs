@@ -0,0 +1,278 @@
+//! T3 策略的本地对战评测，代替 `test-script.sh` 反复调用 `npm run submit-test`
+//!
+//! 用法：`cargo run --release -p tournament -- [--mode 1v1|4snakes|all] [--games N] [--seed S] [--strategies a,b,...]
+//! [--replays DIR] [--replay-format json|bin]`，或者 `... -- --check-replay FILE`、`... -- --show-replay FILE`
+//!
+//! 每局按局号轮换座位，每个策略在各个初始位置上出场的次数相同。参赛策略按固定的搜索深度和迭代次数决策（见 `strategies`），
+//! 不看时间，同样的参数总是得到同样的对局；这个深度比提交的 wasm 导出函数在 200ms 预算内搜到的浅，报告开头会写明。
+//! release 下 4 条蛇的一局约 0.4 秒，默认每种模式 20 局，单线程十秒左右跑完；每种模式的标题行给出总耗时和每局耗时。
+//! 指定 `--replays` 时把每一局的回放（见 `snake_core::replay`）写到 `DIR/<模式>-<局号>.json`（或 `.bin`）。
+//! `--check-replay` 不对战，只重新模拟一局回放（见 `check`）；`--show-replay` 把回放逐回合画出来（见 `show`）。
+
+mod check;
+mod show;
+mod strategies;
+
+use snake_core::game::{GameConfig, GameState, TIME_LIMIT_MS};
+use snake_core::random::RandomGenerator;
+use snake_core::replay::{Move, Replay};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use strategies::{registry, Entry};
+
+struct Options {
+    modes: Vec<String>,
+    games: usize,
+    seed: u64,
+    strategies: Vec<String>,
+    replays: Option<PathBuf>,
+    /// 回放写成二进制格式
+    binary_replays: bool,
+    check_replay: Option<PathBuf>,
+    show_replay: Option<PathBuf>,
+}
+
+/// 单个策略在一种模式下的累计结果
+#[derive(Default)]
+struct Stats {
+    appearances: usize,
+    total_score: i64,
+    survived: usize,
+    death_rounds: Vec<i32>,
+    /// 每次决策的耗时（毫秒）
+    decision_ms: Vec<f64>,
+    timeouts: usize,
+}
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.appearances += other.appearances;
+        self.total_score += other.total_score;
+        self.survived += other.survived;
+        self.death_rounds.extend(other.death_rounds);
+        self.decision_ms.extend(other.decision_ms);
+        self.timeouts += other.timeouts;
+    }
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!("usage: tournament [--mode 1v1|4snakes|all] [--games N] [--seed S] [--strategies a,b,...] [--replays DIR] [--replay-format json|bin] | --check-replay FILE | --show-replay FILE");
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(path) = &options.show_replay {
+        std::process::exit(show::run(path));
+    }
+    let all = registry();
+    if let Some(path) = &options.check_replay {
+        std::process::exit(check::run(path, &all));
+    }
+    let mut entries: Vec<&Entry> = Vec::new();
+    for name in &options.strategies {
+        match all.iter().find(|entry| entry.name == name) {
+            Some(entry) => entries.push(entry),
+            None => {
+                let names: Vec<&str> = all.iter().map(|entry| entry.name).collect();
+                eprintln!("unknown strategy `{name}`, available: {}", names.join(", "));
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(dir) = &options.replays
+        && let Err(error) = std::fs::create_dir_all(dir) {
+        eprintln!("cannot create replay directory {}: {error}", dir.display());
+        std::process::exit(1);
+    }
+
+    println!("{}", strategies::budget_note());
+    println!();
+    for mode in &options.modes {
+        let config = GameConfig::from_mode(mode).expect("mode is checked when parsing");
+        let start = Instant::now();
+        let replays = options.replays.as_deref().map(|dir| ReplayOutput { dir, mode, binary: options.binary_replays });
+        let stats = run_mode(&config, &entries, options.games, options.seed, replays.as_ref());
+        let elapsed = start.elapsed().as_secs_f64();
+        println!("=== {mode}: {} games, board {}x{}, {} snakes, {} foods, {} rounds ({:.2}s, {:.3}s per game) ===",
+            options.games, config.board_size, config.board_size, config.snake_count, config.food_count, config.max_rounds,
+            elapsed, elapsed / options.games.max(1) as f64);
+        print_report(&entries, &stats);
+        println!();
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        modes: vec!["1v1".to_string(), "4snakes".to_string()],
+        games: 20,
+        seed: 2025,
+        strategies: registry().iter().map(|entry| entry.name.to_string()).collect(),
+        replays: None,
+        binary_replays: false,
+        check_replay: None,
+        show_replay: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--mode" => {
+                let mode = value()?;
+                options.modes = match mode.as_str() {
+                    "all" => vec!["1v1".to_string(), "4snakes".to_string()],
+                    _ if GameConfig::from_mode(&mode).is_some() => vec![mode],
+                    _ => return Err(format!("unknown mode `{mode}`")),
+                };
+            }
+            "--games" => options.games = value()?.parse().map_err(|_| "--games expects a number".to_string())?,
+            "--seed" => options.seed = value()?.parse().map_err(|_| "--seed expects a number".to_string())?,
+            "--strategies" => options.strategies = value()?.split(',').map(str::to_string).collect(),
+            "--replays" => options.replays = Some(PathBuf::from(value()?)),
+            "--replay-format" => {
+                options.binary_replays = match value()?.as_str() {
+                    "json" => false,
+                    "bin" => true,
+                    format => return Err(format!("unknown replay format `{format}`")),
+                };
+            }
+            "--check-replay" => options.check_replay = Some(PathBuf::from(value()?)),
+            "--show-replay" => options.show_replay = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("unknown argument `{arg}`")),
+        }
+    }
+    if options.strategies.is_empty() {
+        return Err("no strategy selected".to_string());
+    }
+    Ok(options)
+}
+
+/// 回放文件写到哪里
+struct ReplayOutput<'a> {
+    dir: &'a Path,
+    mode: &'a str,
+    binary: bool,
+}
+
+impl ReplayOutput<'_> {
+    fn write(&self, game: usize, replay: &Replay) {
+        let (extension, bytes) = if self.binary { ("bin", replay.to_bytes()) } else { ("json", replay.to_json().into_bytes()) };
+        let path = self.dir.join(format!("{}-{game:04}.{extension}", self.mode));
+        if let Err(error) = std::fs::write(&path, bytes) {
+            eprintln!("cannot write replay {}: {error}", path.display());
+        }
+    }
+}
+
+/// 把对局平均分给所有线程，返回每个策略的结果
+fn run_mode(config: &GameConfig, entries: &[&Entry], games: usize, seed: u64, replays: Option<&ReplayOutput>) -> Vec<Stats> {
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(games.max(1));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| scope.spawn(move || {
+                let mut stats: Vec<Stats> = entries.iter().map(|_| Stats::default()).collect();
+                for game in (worker..games).step_by(workers) {
+                    play_game(config, entries, game, seed, &mut stats, replays);
+                }
+                stats
+            }))
+            .collect();
+
+        let mut total: Vec<Stats> = entries.iter().map(|_| Stats::default()).collect();
+        for handle in handles {
+            for (sum, part) in total.iter_mut().zip(handle.join().expect("tournament worker panicked")) {
+                sum.merge(part);
+            }
+        }
+        total
+    })
+}
+
+fn play_game(config: &GameConfig, entries: &[&Entry], game: usize, seed: u64, stats: &mut [Stats], replays: Option<&ReplayOutput>) {
+    let mut rng = RandomGenerator::new(seed ^ (game as u64).wrapping_mul(0x9e3779b97f4a7c15));
+    let game_seed = (rng.generate_int(0, i32::MAX) as u64) << 32 | rng.generate_int(0, i32::MAX) as u64;
+    let mut state = GameState::new(config.clone(), game_seed);
+
+    // 第 game 局中座位 seat 上是第 (seat + game) % k 个策略
+    let assignment: Vec<usize> = (0..config.snake_count).map(|seat| (seat + game) % entries.len()).collect();
+    let mut players: Vec<_> = assignment.iter().enumerate()
+        .map(|(seat, index)| (entries[*index].build)(game_seed.wrapping_add(seat as u64)))
+        .collect();
+    let mut replay = Replay::start(&state, assignment.iter().map(|index| entries[*index].name.to_string()).collect());
+
+    while !state.is_over() {
+        let mut actions = Vec::new();
+        let mut moves = Vec::new();
+        for seat in state.alive_seats() {
+            let obs = state.observation(seat).expect("seat is alive");
+            let start = Instant::now();
+            let mut action = players[seat].step(&obs);
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+            let entry_stats = &mut stats[assignment[seat]];
+            entry_stats.decision_ms.push(elapsed);
+            if elapsed > TIME_LIMIT_MS {
+                entry_stats.timeouts += 1;
+                action = state.fallback_action(seat);
+            }
+            actions.push(action);
+            moves.push(Move { seat, action, decision_ms: elapsed });
+        }
+        state.step(&actions);
+        replay.record(moves, &state);
+    }
+    if let Some(output) = replays {
+        output.write(game, &replay);
+    }
+
+    for (seat, index) in assignment.iter().enumerate() {
+        let entry_stats = &mut stats[*index];
+        entry_stats.appearances += 1;
+        entry_stats.total_score += state.get_scores()[seat] as i64;
+        if state.is_alive(seat) {
+            entry_stats.survived += 1;
+        } else {
+            entry_stats.death_rounds.push(state.get_dead_round()[seat]);
+        }
+    }
+}
+
+fn print_report(entries: &[&Entry], stats: &[Stats]) {
+    println!("{:<12} {:>7} {:>9} {:>9} {:>11} {:>11} {:>9} {:>9} {:>9} {:>9} {:>8}",
+        "strategy", "snakes", "avg score", "survival", "avg death", "med death", "p50 ms", "p90 ms", "p99 ms", "max ms", "timeouts");
+    for (entry, stats) in entries.iter().zip(stats) {
+        let appearances = stats.appearances.max(1) as f64;
+        let mut deaths = stats.death_rounds.clone();
+        deaths.sort_unstable();
+        let avg_death = if deaths.is_empty() {
+            "-".to_string()
+        } else {
+            format!("{:.1}", deaths.iter().map(|r| *r as f64).sum::<f64>() / deaths.len() as f64)
+        };
+        let med_death = deaths.get(deaths.len() / 2).map_or("-".to_string(), |r| r.to_string());
+        let mut times = stats.decision_ms.clone();
+        times.sort_unstable_by(f64::total_cmp);
+        println!("{:<12} {:>7} {:>9.3} {:>8.1}% {:>11} {:>11} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>8}",
+            entry.name,
+            stats.appearances,
+            stats.total_score as f64 / appearances,
+            stats.survived as f64 * 100.0 / appearances,
+            avg_death,
+            med_death,
+            percentile(&times, 0.5),
+            percentile(&times, 0.9),
+            percentile(&times, 0.99),
+            times.last().copied().unwrap_or(0.0),
+            stats.timeouts);
+    }
+}
+
+/// `sorted` 需要已经升序排列
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
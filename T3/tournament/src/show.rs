@@ -0,0 +1,42 @@
+//! `--show-replay`：在终端里一回合一回合地画出回放
+//!
+//! 每回合画出结算之前的局面和各条蛇记录的动作。输出到终端时上色，重定向到文件或设置了 `NO_COLOR` 时不上色。
+//! 蛇头的字母按存活的座位顺序分配（见 `Board::from_game`），有蛇死掉之后后面的字母会前移。
+
+use crate::check;
+use snake_core::notation::Board;
+use snake_core::render::{render, RenderOptions};
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// 画出 `path` 中的回放，返回进程的退出码
+pub fn run(path: &Path) -> i32 {
+    let replay = match check::read(path) {
+        Ok(replay) => replay,
+        Err(message) => {
+            eprintln!("cannot read replay {}: {message}", path.display());
+            return 2;
+        }
+    };
+    let options = RenderOptions { ansi: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(), ..Default::default() };
+
+    let result = replay.resimulate(|state, turn| {
+        let moves: Vec<String> = turn.moves.iter()
+            .map(|m| format!("{}:{}", replay.strategies.get(m.seat).map_or("?", String::as_str), check::direction_name(m.action)))
+            .collect();
+        println!("round {}  scores {:?}  moves {}", state.get_round() + 1, state.get_scores(), moves.join(" "));
+        print!("{}", render(&Board::from_game(state).to_map(), &options));
+        println!();
+    });
+    match result {
+        Ok(state) => {
+            println!("final scores {:?}", state.get_scores());
+            print!("{}", render(&Board::from_game(&state).to_map(), &options));
+            0
+        }
+        Err(divergence) => {
+            eprintln!("replay does not match the rules: {divergence}");
+            1
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use snake_core::game::Observation;
+use snake_core::map::{Direction, Map, Position, Snake};
+use snake_core::random::RandomGenerator;
+
+/// 参加对战的一条蛇，每局对局开始时重新创建
+pub trait Strategy {
+    fn step(&mut self, obs: &Observation) -> i32;
+}
+
+/// 可以参赛的策略
+pub struct Entry {
+    pub name: &'static str,
+    pub build: fn(seed: u64) -> Box<dyn Strategy>,
+}
+
+/// 对战中对抗搜索的固定深度，不限时间
+///
+/// 评测时的决策只由局面决定，同样的参数总是得到同样的对局，也不受机器负载影响；
+/// 深度比 wasm 导出函数在时间预算内通常能搜到的浅，单次决策只要几毫秒
+const SEARCH_DEPTH: i32 = 2;
+/// 对战中 MCTS 的固定迭代次数，不限时间
+const MCTS_ITERATIONS: u32 = 200;
+
+/// 写在报告开头：参赛策略的决策预算与提交的 wasm 导出函数不同，成绩只能在这个预算下互相比较
+pub fn budget_note() -> String {
+    format!("note: greedy/greedy-agent search to a fixed depth of {SEARCH_DEPTH} and mcts runs {MCTS_ITERATIONS} iterations, with no time limit; \
+        the wasm exports search up to {} ms (greedy) and {} ms (mcts) per move, so scores here compare these fixed budgets, not the shipped bots",
+        t3_rust::SEARCH_BUDGET_MS, t3_rust::MCTS_BUDGET_MS)
+}
+
+/// 工作区中所有的策略，新增策略时在这里登记
+pub fn registry() -> Vec<Entry> {
+    vec![
+        Entry { name: "greedy", build: |_| Box::new(Agent { agent: None, keep: false }) },
+        Entry { name: "greedy-agent", build: |_| Box::new(Agent { agent: None, keep: true }) },
+        Entry { name: "mcts", build: |_| Box::new(Mcts) },
+        Entry { name: "random", build: |seed| Box::new(RandomSafe(RandomGenerator::new(seed))) },
+    ]
+}
+
+/// 按 `SEARCH_DEPTH` 搜索的 `SnakeAgent`，棋盘大小在第一回合才知道
+///
+/// `keep` 为真时整局共用一个，否则与 `greedy_snake_step` 一样每回合从头决策
+struct Agent {
+    agent: Option<t3_rust::SnakeAgent>,
+    keep: bool,
+}
+
+impl Strategy for Agent {
+    fn step(&mut self, obs: &Observation) -> i32 {
+        if !self.keep {
+            self.agent = None;
+        }
+        let agent = self.agent.get_or_insert_with(|| {
+            let mut agent = t3_rust::SnakeAgent::new(obs.board_size);
+            agent.set_search_budget(SEARCH_DEPTH, f64::INFINITY);
+            agent
+        });
+        agent.step(obs.my_snake.clone(), obs.snake_num, obs.other_snakes.clone(), obs.food_num, obs.foods.clone(), obs.round)
+    }
+}
+
+/// 按 `MCTS_ITERATIONS` 搜索的 `mcts_snake_step`
+struct Mcts;
+
+impl Strategy for Mcts {
+    fn step(&mut self, obs: &Observation) -> i32 {
+        t3_rust::mcts_snake_step_with_budget(obs.board_size, obs.my_snake.clone(), obs.snake_num, obs.other_snakes.clone(), obs.food_num, obs.foods.clone(), obs.round,
+            MCTS_ITERATIONS, f64::INFINITY)
+    }
+}
+
+/// 对照组：在不会立即撞死的方向中随机选一个
+struct RandomSafe(RandomGenerator);
+
+impl Strategy for RandomSafe {
+    fn step(&mut self, obs: &Observation) -> i32 {
+        let map = Map::new(to_snake(&obs.my_snake), obs.other_snakes.chunks(8).map(to_snake).collect(), vec![], obs.board_size);
+        let head = *map.get_my_snake().get_head();
+        let safe: Vec<Direction> = Direction::iter().filter(|d| !map.strict_obstacles_check(&head.step(*d))).collect();
+        if safe.is_empty() {
+            return Direction::Up.to_code();
+        }
+        safe[self.0.generate_int(0, safe.len() as i32 - 1) as usize].to_code()
+    }
+}
+
+fn to_snake(coords: &[i32]) -> Snake {
+    Snake::from_body(coords.chunks(2).map(|pair| Position::new(pair[0], pair[1])).collect())
+}
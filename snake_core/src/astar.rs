@@ -0,0 +1,214 @@
+use crate::bitboard::BoardState;
+use crate::map::{Direction, Map, Position};
+use std::collections::{BinaryHeap, HashSet};
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pos: Position,
+    g: i32,
+    h: i32,
+    f: i32,
+    parent: Option<Box<Node>>
+}
+
+impl Node {
+    pub fn new(pos: Position, g: i32, h: i32, parent: Option<Node>) -> Node {
+        Node {pos, g, h, f: g + h, parent: parent.map(Box::new)}
+    }
+
+    pub fn heuristic(a: &Position, b: &Position) -> i32 {
+        a.manhattan(b)
+    }
+
+    pub fn get_pos(&self) -> &Position {
+        &self.pos
+    }
+
+    pub fn get_g(&self) -> i32 {
+        self.g
+    }
+
+    pub fn get_h(&self) -> i32 {
+        self.h
+    }
+
+    pub fn get_f(&self) -> i32 {
+        self.f
+    }
+
+    pub fn get_parent(&self) -> &Option<Box<Node>> {
+        &self.parent
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.f == other.f
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.f.cmp(&self.f)
+    }
+}
+
+/// 从我方蛇头到 `goal` 的 A* 路径，用 `Map::colision_check_at` 判断可否通行
+///
+/// 节点的 g 即走到该格所用的步数，蛇身会随步数让出格子，所以同一个格子在不同步数下
+/// 是不同的状态；步数达到蛇长之后所有蛇身都已离开，不必再区分。
+/// 找不到路径时返回空数组
+pub fn find_astar_path(map: &Map, goal: &Position) -> Vec<Direction> {
+    let head = *map.get_my_snake().get_head();
+    let horizon = map.get_my_snake().get_body().len() as i32;
+
+    let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
+    let mut closed_set: HashSet<(Position, i32)> = HashSet::new();
+    open_set.push(Node::new(head, 0, Node::heuristic(&head, goal), None));
+
+    while let Some(cur) = open_set.pop() {
+        if cur.get_pos() == goal {
+            return reconstruct_path(&cur);
+        }
+
+        if !closed_set.insert((*cur.get_pos(), cur.get_g().min(horizon))) {
+            continue;
+        }
+
+        let g = cur.get_g() + 1;
+        for direction in Direction::iter() {
+            let neighbor = cur.get_pos().step(direction);
+            if (!closed_set.contains(&(neighbor, g.min(horizon)))) && (!map.colision_check_at(&neighbor, g)) {
+                let h = Node::heuristic(&neighbor, goal);
+                open_set.push(Node::new(neighbor, g, h, Some(cur.clone())));
+            }
+        }
+    }
+
+    vec![]
+}
+
+/// 单蛇场地（`Map::with_barriers`）上从我方蛇头到 `goal` 的最短路径，长度与 `find_astar_path` 相同
+///
+/// 局面放得进位棋盘（见 `BoardState::fits`）时直接在 `BoardState` 上搜索，否则退回 `find_astar_path`。
+/// 找不到路径时返回空数组
+pub fn find_barrier_path(map: &Map, goal: &Position) -> Vec<Direction> {
+    debug_assert!(map.get_other_snakes().is_empty(), "find_barrier_path ignores other snakes");
+    match BoardState::try_from_map(map) {
+        Some(board) => board.find_path(0, goal),
+        None => find_astar_path(map, goal),
+    }
+}
+
+/// 对每个果子分别寻路，返回其中最短的一条
+pub fn find_nearest_food_path(map: &Map) -> Vec<Direction> {
+    find_food_paths(map).into_iter().next().unwrap_or_default()
+}
+
+/// 到每个可达果子的路径，按长度从短到长排列
+pub fn find_food_paths(map: &Map) -> Vec<Vec<Direction>> {
+    let mut paths: Vec<Vec<Direction>> = map.get_foods().iter()
+        .map(|food| find_astar_path(map, food))
+        .filter(|path| !path.is_empty())
+        .collect();
+    paths.sort_by_key(|path| path.len());
+    paths
+}
+
+pub fn reconstruct_path(node: &Node) -> Vec<Direction> {
+    let mut path: Vec<Direction> = Vec::new();
+    let mut current_node = node;
+
+    while let Some(parent) = current_node.get_parent() {
+        let from = parent.get_pos();
+        let to = current_node.get_pos();
+        if let Some(direction) = Direction::iter().find(|d| &from.step(*d) == to) {
+            path.push(direction);
+        }
+        current_node = parent;
+    }
+
+    path.reverse();
+    path
+}
+
+/// 把完整路线编码成导出给 JS 的数组：`[步数, 目标 x, 目标 y, 每一步的方向编号...]`
+pub fn encode_path(target: &Position, path: &[Direction]) -> Vec<i32> {
+    let mut codes = vec![path.len() as i32, target.get_x(), target.get_y()];
+    codes.extend(path.iter().map(|direction| direction.to_code()));
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    #[test]
+    fn test_straight_path() {
+        let map = Map::with_barriers(snake(&[1, 1, 1, 2, 1, 3, 1, 4]), vec![], vec![], 8);
+        let path = find_astar_path(&map, &Position::new(4, 1));
+        assert_eq!(path, vec![Direction::Right; 3]);
+    }
+
+    #[test]
+    fn test_path_is_shortest_around_barriers() {
+        let barriers = vec![Position::new(2, 1), Position::new(2, 2)];
+        let map = Map::with_barriers(snake(&[1, 1, 1, 2, 1, 3, 1, 4]), vec![], barriers, 8);
+        // 蛇身和障碍把 (1,1) 围住，走不出去
+        assert!(find_astar_path(&map, &Position::new(4, 1)).is_empty());
+
+        let barriers = vec![Position::new(3, 1), Position::new(3, 2)];
+        let map = Map::with_barriers(snake(&[1, 1, 1, 2, 1, 3, 1, 4]), vec![], barriers, 8);
+        assert_eq!(find_astar_path(&map, &Position::new(4, 1)).len(), 7);
+    }
+
+    #[test]
+    fn test_body_moves_out_of_the_way() {
+        // 蛇盘成一圈，(1,3) 被堵住，只能等蛇身第 2 节让出 (2,2) 后从那里出去
+        let barriers = vec![Position::new(1, 3)];
+        let map = Map::with_barriers(snake(&[1, 1, 2, 1, 2, 2, 1, 2]), vec![], barriers, 8);
+        let path = find_astar_path(&map, &Position::new(5, 5));
+        assert_eq!(path.len(), 8);
+        assert_eq!(&path[0..2], &[Direction::Up, Direction::Right]);
+        assert!(map.colision_check(&Position::new(2, 2)));
+        assert!(!map.colision_check_at(&Position::new(2, 2), 2));
+    }
+
+    #[test]
+    fn test_enter_neck_after_it_leaves() {
+        // (2,1) 是蛇身第 1 节，第 3 步才让出；绕一圈后正好赶上，再穿过已经让出的 (3,1)
+        let barriers = vec![Position::new(3, 2), Position::new(1, 3), Position::new(2, 3)];
+        let map = Map::with_barriers(snake(&[1, 1, 2, 1, 3, 1, 4, 1]), vec![], barriers, 8);
+        let path = find_astar_path(&map, &Position::new(4, 1));
+        assert_eq!(path, vec![Direction::Up, Direction::Right, Direction::Down, Direction::Right, Direction::Right]);
+        assert!(map.colision_check_at(&Position::new(2, 1), 2));
+    }
+
+    #[test]
+    fn test_barrier_path_same_length() {
+        // 16×16 在位棋盘上搜索，20×20 超出位棋盘，退回 A*
+        for size in [8, 16, 20] {
+            let barriers = vec![Position::new(3, 2), Position::new(1, 3), Position::new(2, 3)];
+            let map = Map::with_barriers(snake(&[1, 1, 2, 1, 3, 1, 4, 1]), vec![], barriers, size);
+            for goal in [Position::new(4, 1), Position::new(size, size), Position::new(1, 2)] {
+                assert_eq!(find_barrier_path(&map, &goal).len(), find_astar_path(&map, &goal).len(), "{size} {goal:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest_food() {
+        let foods = vec![Position::new(5, 5), Position::new(1, 6)];
+        let map = Map::new(snake(&[3, 5, 2, 5, 1, 5, 1, 4]), vec![], foods, 8);
+        assert_eq!(find_nearest_food_path(&map).len(), 2);
+    }
+}
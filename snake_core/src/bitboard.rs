@@ -0,0 +1,536 @@
+//! 位棋盘表示的局面，给需要大量模拟的搜索使用
+//!
+//! 棋盘最大 16×16，格子 (x, y) 对应第 (x-1)*16 + (y-1) 位，占用、果子、蛇头、障碍各用一个
+//! 256 位的位图，查询都是 O(1)。蛇身用环形数组保存，走一步只需改写蛇尾所在的位置。
+//! 整个局面是 `Copy` 的，复制就是一次内存拷贝。
+
+use crate::map::{Direction, Map, Position, Snake};
+
+/// 支持的最大棋盘边长
+pub const MAX_SIZE: i32 = 16;
+/// 支持的最多蛇数
+pub const MAX_SNAKES: usize = 8;
+/// 支持的最长蛇身
+pub const MAX_SNAKE_LENGTH: usize = 16;
+
+/// 256 位的位图，每一位对应棋盘上的一个格子
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Bits([u64; 4]);
+
+impl Bits {
+    pub fn get(&self, cell: u8) -> bool {
+        self.0[(cell >> 6) as usize] >> (cell & 63) & 1 == 1
+    }
+
+    pub fn set(&mut self, cell: u8) {
+        self.0[(cell >> 6) as usize] |= 1 << (cell & 63);
+    }
+
+    pub fn clear(&mut self, cell: u8) {
+        self.0[(cell >> 6) as usize] &= !(1 << (cell & 63));
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    /// 所有置位的格子
+    pub fn cells(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..4).flat_map(move |i| {
+            let mut word = self.0[i];
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros();
+                word &= word - 1;
+                Some((i as u32 * 64 + bit) as u8)
+            })
+        })
+    }
+}
+
+/// 一条蛇，`cells[(head + k) % len]` 是第 k 节
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Body {
+    cells: [u8; MAX_SNAKE_LENGTH],
+    head: u8,
+    len: u8,
+    alive: bool,
+}
+
+impl Body {
+    fn segment(&self, k: usize) -> u8 {
+        self.cells[(self.head as usize + k) % self.len as usize]
+    }
+
+    fn tail(&self) -> u8 {
+        self.segment(self.len as usize - 1)
+    }
+}
+
+/// `apply_joint_moves` 的结果，第 i 位对应第 i 条蛇
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MoveOutcome {
+    /// 本回合死亡的蛇
+    pub died: u8,
+    /// 本回合吃到果子的蛇
+    pub ate: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BoardState {
+    size: i32,
+    /// 存活的蛇的身体占用的格子
+    occupied: Bits,
+    foods: Bits,
+    heads: Bits,
+    barriers: Bits,
+    snakes: [Body; MAX_SNAKES],
+    snake_count: u8,
+}
+
+impl BoardState {
+
+    /// 棋盘边长超过 `MAX_SIZE`、蛇数超过 `MAX_SNAKES` 或蛇身超过 `MAX_SNAKE_LENGTH` 时 panic，所有坐标都要在棋盘内
+    pub fn new(size: i32, snakes: &[Snake], foods: &[Position], barriers: &[Position]) -> BoardState {
+        if !(1..=MAX_SIZE).contains(&size) {
+            panic!("board size {} is not supported, the maximum is {}", size, MAX_SIZE);
+        }
+        if snakes.len() > MAX_SNAKES {
+            panic!("{} snakes are not supported, the maximum is {}", snakes.len(), MAX_SNAKES);
+        }
+        let empty = Body { cells: [0; MAX_SNAKE_LENGTH], head: 0, len: 1, alive: false };
+        let mut board = BoardState {
+            size,
+            occupied: Bits::default(),
+            foods: Bits::default(),
+            heads: Bits::default(),
+            barriers: Bits::default(),
+            snakes: [empty; MAX_SNAKES],
+            snake_count: snakes.len() as u8,
+        };
+        for (i, snake) in snakes.iter().enumerate() {
+            let body = snake.get_body();
+            if body.is_empty() || body.len() > MAX_SNAKE_LENGTH {
+                panic!("snake length {} is not supported, the maximum is {}", body.len(), MAX_SNAKE_LENGTH);
+            }
+            let mut cells = [0; MAX_SNAKE_LENGTH];
+            for (k, pos) in body.iter().enumerate() {
+                cells[k] = board.cell(pos);
+                board.occupied.set(cells[k]);
+            }
+            board.heads.set(cells[0]);
+            board.snakes[i] = Body { cells, head: 0, len: body.len() as u8, alive: true };
+        }
+        for pos in foods {
+            board.foods.set(board.cell(pos));
+        }
+        for pos in barriers {
+            board.barriers.set(board.cell(pos));
+        }
+        board
+    }
+
+    /// 棋盘边长、蛇数和每条蛇的长度是否都在支持的范围内，是的话 `new` 不会 panic
+    pub fn fits(size: i32, snakes: &[Snake]) -> bool {
+        (1..=MAX_SIZE).contains(&size)
+            && snakes.len() <= MAX_SNAKES
+            && snakes.iter().all(|snake| (1..=MAX_SNAKE_LENGTH).contains(&snake.get_body().len()))
+    }
+
+    /// 与 `from_map` 相同，局面超出 `fits` 支持的范围时返回 `None`
+    pub fn try_from_map(map: &Map) -> Option<BoardState> {
+        let snakes: Vec<Snake> = std::iter::once(map.get_my_snake()).chain(map.get_other_snakes()).cloned().collect();
+        BoardState::fits(map.get_size(), &snakes).then(|| BoardState::from_map(map))
+    }
+
+    /// 由 `Map` 构造，我方是第 0 条蛇，其他蛇依次排在后面
+    pub fn from_map(map: &Map) -> BoardState {
+        let mut snakes = vec![map.get_my_snake().clone()];
+        snakes.extend(map.get_other_snakes().iter().cloned());
+        BoardState::new(map.get_size(), &snakes, map.get_foods(), map.get_barriers())
+    }
+
+    pub fn in_board(&self, pos: &Position) -> bool {
+        pos.get_x() >= 1 && pos.get_x() <= self.size && pos.get_y() >= 1 && pos.get_y() <= self.size
+    }
+
+    /// 棋盘内格子的编号，调用方需要保证 `pos` 在棋盘内，否则会落到别的格子上
+    pub fn cell(&self, pos: &Position) -> u8 {
+        debug_assert!(self.in_board(pos), "({}, {}) is outside the {}x{} board", pos.get_x(), pos.get_y(), self.size, self.size);
+        ((pos.get_x() - 1) * MAX_SIZE + pos.get_y() - 1) as u8
+    }
+
+    pub fn position(&self, cell: u8) -> Position {
+        Position::new(cell as i32 / MAX_SIZE + 1, cell as i32 % MAX_SIZE + 1)
+    }
+
+    pub fn is_occupied(&self, pos: &Position) -> bool {
+        self.in_board(pos) && self.occupied.get(self.cell(pos))
+    }
+
+    pub fn has_food(&self, pos: &Position) -> bool {
+        self.in_board(pos) && self.foods.get(self.cell(pos))
+    }
+
+    pub fn is_head(&self, pos: &Position) -> bool {
+        self.in_board(pos) && self.heads.get(self.cell(pos))
+    }
+
+    pub fn is_barrier(&self, pos: &Position) -> bool {
+        self.in_board(pos) && self.barriers.get(self.cell(pos))
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn snake_count(&self) -> usize {
+        self.snake_count as usize
+    }
+
+    pub fn is_alive(&self, i: usize) -> bool {
+        self.snakes[i].alive
+    }
+
+    pub fn alive_count(&self) -> usize {
+        (0..self.snake_count()).filter(|i| self.is_alive(*i)).count()
+    }
+
+    /// 死蛇返回 `None`
+    pub fn head(&self, i: usize) -> Option<Position> {
+        let body = &self.snakes[i];
+        body.alive.then(|| self.position(body.segment(0)))
+    }
+
+    /// 从蛇头到蛇尾的身体，死蛇返回空数组
+    pub fn body(&self, i: usize) -> Vec<Position> {
+        let body = &self.snakes[i];
+        if !body.alive {
+            return vec![];
+        }
+        (0..body.len as usize).map(|k| self.position(body.segment(k))).collect()
+    }
+
+    /// 两个局面的棋盘、果子、障碍和每条蛇的身体是否完全相同
+    ///
+    /// 蛇身在环形缓冲区中的起点可能不同，所以不能直接比较 `==`
+    pub fn same_position(&self, other: &BoardState) -> bool {
+        self.size == other.size
+            && self.snake_count == other.snake_count
+            && self.foods == other.foods
+            && self.barriers == other.barriers
+            && (0..self.snake_count()).all(|i| self.is_alive(i) == other.is_alive(i) && self.body(i) == other.body(i))
+    }
+
+    pub fn foods(&self) -> Vec<Position> {
+        self.foods.cells().map(|cell| self.position(cell)).collect()
+    }
+
+    pub fn food_count(&self) -> u32 {
+        self.foods.count()
+    }
+
+    /// 第 `i` 条蛇走向 `direction` 是否一定会撞死：出界、障碍，或者任意一条蛇不会让出的身体
+    pub fn is_deadly(&self, i: usize, direction: Direction) -> bool {
+        let Some(head) = self.head(i) else {
+            return true;
+        };
+        let pos = head.step(direction);
+        if !self.in_board(&pos) {
+            return true;
+        }
+        let cell = self.cell(&pos);
+        if self.barriers.get(cell) {
+            return true;
+        }
+        if !self.occupied.get(cell) {
+            return false;
+        }
+        // 所有蛇移动后都会让出蛇尾
+        !(0..self.snake_count()).any(|j| self.snakes[j].alive && self.snakes[j].tail() == cell)
+    }
+
+    /// 所有蛇同时移动，规则与 `board::move_snakes` 相同，撞上障碍同样死亡，然后吃掉果子（不补充）
+    ///
+    /// `moves` 与蛇一一对应，死蛇的动作被忽略，活蛇的动作为 `None` 时直接死亡
+    pub fn apply_joint_moves(&mut self, moves: &[Option<Direction>]) -> MoveOutcome {
+        let count = self.snake_count();
+        let mut new_heads: [Option<u8>; MAX_SNAKES] = [None; MAX_SNAKES];
+        let mut outcome = MoveOutcome::default();
+
+        // 移动后所有蛇的 body[1..] 就是移动前除蛇尾外的身体；没有动作的蛇不在场上，不会挡住别人
+        let mut necks = Bits::default();
+        for (i, (body, new_head)) in self.snakes.iter().zip(new_heads.iter_mut()).enumerate().take(count) {
+            let Some(direction) = moves.get(i).copied().flatten().filter(|_| body.alive) else {
+                if body.alive {
+                    outcome.died |= 1 << i;
+                }
+                continue;
+            };
+            for k in 0..body.len as usize - 1 {
+                necks.set(body.segment(k));
+            }
+            let pos = self.position(body.segment(0)).step(direction);
+            if self.in_board(&pos) {
+                *new_head = Some(self.cell(&pos));
+            } else {
+                outcome.died |= 1 << i;
+            }
+        }
+
+        for (i, new_head) in new_heads.iter().enumerate() {
+            let Some(cell) = *new_head else {
+                continue;
+            };
+            let head_on = new_heads.iter().enumerate().any(|(j, other)| j != i && *other == Some(cell));
+            if necks.get(cell) || self.barriers.get(cell) || head_on {
+                outcome.died |= 1 << i;
+            }
+        }
+
+        for (i, (body, new_head)) in self.snakes.iter_mut().zip(new_heads).enumerate().take(count) {
+            if !body.alive {
+                continue;
+            }
+            for k in 0..body.len as usize {
+                self.occupied.clear(body.segment(k));
+            }
+            self.heads.clear(body.segment(0));
+            if outcome.died >> i & 1 == 1 {
+                body.alive = false;
+                continue;
+            }
+            let cell = new_head.expect("alive snake has a new head");
+            // 环形数组往前挪一格，新蛇头覆盖原来的蛇尾
+            body.head = (body.head + body.len - 1) % body.len;
+            let head = body.head as usize;
+            body.cells[head] = cell;
+        }
+        for i in 0..count {
+            let body = self.snakes[i];
+            if !body.alive {
+                continue;
+            }
+            for k in 0..body.len as usize {
+                self.occupied.set(body.segment(k));
+            }
+            let head = body.segment(0);
+            self.heads.set(head);
+            if self.foods.get(head) {
+                self.foods.clear(head);
+                outcome.ate |= 1 << i;
+            }
+        }
+        outcome
+    }
+
+    /// 第 `i` 条蛇的蛇头在第 `steps` 步到达 `start` 之后还能走到的格子数，数到 `limit` 为止
+    ///
+    /// 与 `Map::reachable_area` 相同：第 `i` 条蛇的身体按步数逐节让出，其他蛇的整条身体都当成墙
+    pub fn reachable_area(&self, i: usize, start: &Position, steps: i32, limit: usize) -> usize {
+        let mut walls = self.barriers;
+        let mut own = Bits::default();
+        for j in 0..self.snake_count() {
+            let body = &self.snakes[j];
+            if !body.alive {
+                continue;
+            }
+            for k in 0..body.len as usize {
+                if j == i { own.set(body.segment(k)) } else { walls.set(body.segment(k)) }
+            }
+        }
+        let len = self.snakes[i].len as i32;
+        let own_index = |cell: u8| (0..len as usize).find(|k| self.snakes[i].segment(*k) == cell).unwrap_or(0) as i32;
+        let blocked = |pos: &Position, depth: i32| {
+            if !self.in_board(pos) {
+                return true;
+            }
+            let cell = self.cell(pos);
+            walls.get(cell) || (own.get(cell) && own_index(cell) + depth < len)
+        };
+        if blocked(start, steps) {
+            return 0;
+        }
+
+        let mut visited = Bits::default();
+        visited.set(self.cell(start));
+        let mut frontier = vec![*start];
+        let mut count = 1;
+        let mut depth = steps;
+        while !frontier.is_empty() && count < limit {
+            depth += 1;
+            let mut next = Vec::new();
+            for pos in &frontier {
+                for direction in Direction::iter() {
+                    let neighbor = pos.step(direction);
+                    if !blocked(&neighbor, depth) && !visited.get(self.cell(&neighbor)) {
+                        visited.set(self.cell(&neighbor));
+                        next.push(neighbor);
+                        count += 1;
+                    }
+                }
+            }
+            frontier = next;
+        }
+        count.min(limit)
+    }
+
+    /// 第 `i` 条蛇到 `goal` 的最短路径，考虑自己和其他蛇的身体随步数让出，找不到时返回空数组
+    ///
+    /// 其他蛇被看成沿着自己的身体原地不动地缩短，与 `find_astar_path` 的严格检查一致
+    pub fn find_path(&self, i: usize, goal: &Position) -> Vec<Direction> {
+        let Some(start) = self.head(i) else {
+            return vec![];
+        };
+        // 每个格子在第几步之后空出来
+        let mut free_after = [0i32; 256];
+        for j in 0..self.snake_count() {
+            let body = &self.snakes[j];
+            if !body.alive {
+                continue;
+            }
+            for k in 0..body.len as usize {
+                free_after[body.segment(k) as usize] = (body.len as usize - k) as i32;
+            }
+        }
+        let horizon = self.snakes[i].len as i32;
+
+        // 状态为 (格子, min(步数, horizon))，步数达到蛇长后所有蛇身都已离开
+        let key = |cell: u8, steps: i32| cell as usize * (MAX_SNAKE_LENGTH + 1) + steps.min(horizon) as usize;
+        let mut parent: Vec<Option<(usize, Direction)>> = vec![None; 256 * (MAX_SNAKE_LENGTH + 1)];
+        let mut seen = vec![false; 256 * (MAX_SNAKE_LENGTH + 1)];
+        let start_key = key(self.cell(&start), 0);
+        seen[start_key] = true;
+        let mut frontier = vec![(start, start_key)];
+        let mut steps = 0;
+        while !frontier.is_empty() {
+            steps += 1;
+            let mut next = Vec::new();
+            for (pos, from) in frontier {
+                for direction in Direction::iter() {
+                    let neighbor = pos.step(direction);
+                    if !self.in_board(&neighbor) {
+                        continue;
+                    }
+                    let cell = self.cell(&neighbor);
+                    if self.barriers.get(cell) || free_after[cell as usize] > steps {
+                        continue;
+                    }
+                    let to = key(cell, steps);
+                    if seen[to] {
+                        continue;
+                    }
+                    seen[to] = true;
+                    parent[to] = Some((from, direction));
+                    if neighbor == *goal {
+                        let mut path = vec![];
+                        let mut current = to;
+                        while let Some((prev, direction)) = parent[current] {
+                            path.push(direction);
+                            current = prev;
+                        }
+                        path.reverse();
+                        return path;
+                    }
+                    next.push((neighbor, to));
+                }
+            }
+            frontier = next;
+        }
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+    use crate::astar::find_astar_path;
+    use crate::board::move_snakes;
+    use crate::random::RandomGenerator;
+
+    #[test]
+    fn test_queries() {
+        let board = BoardState::new(16, &[snake(&[16, 16, 16, 15, 15, 15, 14, 15])], &[Position::new(1, 1)], &[Position::new(8, 9)]);
+        assert!(board.is_occupied(&Position::new(14, 15)));
+        assert!(!board.is_occupied(&Position::new(14, 16)));
+        assert!(board.is_head(&Position::new(16, 16)));
+        assert!(board.has_food(&Position::new(1, 1)));
+        assert!(board.is_barrier(&Position::new(8, 9)));
+        assert!(!board.is_occupied(&Position::new(17, 1)));
+        assert_eq!(board.body(0), snake(&[16, 16, 16, 15, 15, 15, 14, 15]).get_body());
+    }
+
+    #[test]
+    fn test_same_as_referee() {
+        // 随机走子，与 `board::move_snakes` 逐回合对比
+        let mut rng = RandomGenerator::new(7);
+        for _ in 0..300 {
+            let mut snakes = [
+                Some(snake(&[4, 1, 3, 1, 2, 1, 1, 1])),
+                Some(snake(&[8, 4, 8, 3, 8, 2, 8, 1])),
+                Some(snake(&[5, 8, 6, 8, 7, 8, 8, 8])),
+                Some(snake(&[1, 5, 1, 6, 1, 7, 1, 8])),
+            ];
+            let all: Vec<Snake> = snakes.iter().flatten().cloned().collect();
+            let mut board = BoardState::new(8, &all, &[Position::new(4, 4), Position::new(5, 2)], &[]);
+            for _ in 0..40 {
+                let moves: Vec<Option<Direction>> = snakes.iter()
+                    .map(|_| Direction::from_code(rng.generate_float(0.0, 4.0) as i32))
+                    .collect();
+                let alive: Vec<usize> = (0..snakes.len()).filter(|i| snakes[*i].is_some()).collect();
+                if alive.is_empty() {
+                    break;
+                }
+                let moved = move_snakes(8, &alive.iter().map(|i| snakes[*i].clone().unwrap()).collect::<Vec<_>>(), &alive.iter().map(|i| moves[*i]).collect::<Vec<_>>());
+                for (i, snake) in alive.into_iter().zip(moved) {
+                    snakes[i] = snake;
+                }
+                board.apply_joint_moves(&moves);
+                for (i, snake) in snakes.iter().enumerate() {
+                    assert_eq!(board.body(i), snake.as_ref().map(|s| s.get_body().to_vec()).unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_eat_and_head_on() {
+        let mut board = BoardState::new(5, &[snake(&[1, 3, 1, 2, 1, 1, 2, 1]), snake(&[3, 3, 4, 3, 5, 3, 5, 4])], &[Position::new(1, 4)], &[]);
+        let outcome = board.apply_joint_moves(&[Some(Direction::Up), Some(Direction::Down)]);
+        assert_eq!(outcome, MoveOutcome { died: 0, ate: 1 });
+        assert_eq!(board.food_count(), 0);
+        // 两个蛇头同时进入 (2,3)
+        let mut board = BoardState::new(5, &[snake(&[1, 3, 1, 2, 1, 1, 2, 1]), snake(&[3, 3, 4, 3, 5, 3, 5, 4])], &[], &[]);
+        let outcome = board.apply_joint_moves(&[Some(Direction::Right), Some(Direction::Left)]);
+        assert_eq!(outcome.died, 0b11);
+        assert_eq!(board.alive_count(), 0);
+        assert!(!board.is_occupied(&Position::new(1, 1)));
+    }
+
+    #[test]
+    fn test_matches_map_queries() {
+        let barriers = vec![Position::new(3, 2), Position::new(1, 3), Position::new(2, 3)];
+        let map = Map::with_barriers(snake(&[1, 1, 2, 1, 3, 1, 4, 1]), vec![], barriers, 8);
+        let board = BoardState::from_map(&map);
+        let goal = Position::new(4, 1);
+        assert_eq!(board.find_path(0, &goal).len(), find_astar_path(&map, &goal).len());
+        assert!(board.is_deadly(0, Direction::Right));
+        assert!(!board.is_deadly(0, Direction::Up));
+
+        let map = Map::new(snake(&[1, 4, 1, 5, 2, 5, 3, 5]), vec![snake(&[3, 1, 2, 1, 2, 2, 2, 3])], vec![], 5);
+        let board = BoardState::from_map(&map);
+        for x in 1..=5 {
+            for y in 1..=5 {
+                let pos = Position::new(x, y);
+                assert_eq!(board.reachable_area(0, &pos, 1, usize::MAX), map.reachable_area(&pos, 1, usize::MAX));
+            }
+        }
+    }
+}
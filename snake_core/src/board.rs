@@ -0,0 +1,401 @@
+//! 多蛇对局（T3）的裁判逻辑，对应 `T3/board/pkg` 中预编译的 `process_turn` / `initial_foods`
+//!
+//! 一个回合内所有蛇同时移动，然后统一判定：
+//! 1. 蛇头出界，或与任意一条蛇（包括本回合同样死亡的蛇）移动后的身体重合，死亡；
+//! 2. 两个及以上蛇头进入同一格，全部死亡；
+//! 3. 碰撞优先于吃果子，死亡的蛇不会吃掉所在格的果子；
+//! 4. 回合结束前补充果子，新果子不会出现在存活的蛇身上或已有果子上。
+//!
+//! 新果子的位置由 `seed` 和当前局面共同决定，同样的输入总是得到同样的输出，
+//! 但不保证与预编译模块逐格一致。
+//!
+//! 输入不合法（数组长度不对、蛇身不连续、棋盘太小）时返回 `InputError`，不会 panic。
+
+use crate::input::{self, check_range, InputError};
+use crate::map::{Direction, Position, Snake};
+use crate::random::RandomGenerator;
+
+/// 蛇的长度，吃到果子也不会变长
+pub const SNAKE_LENGTH: usize = 4;
+/// 每条蛇在输入数组中占用的 i32 个数
+pub const SNAKE_STRIDE: usize = SNAKE_LENGTH * 2;
+
+/// 蛇在本回合死亡
+pub const STATUS_DEAD: i32 = -1;
+/// 蛇存活且没有吃到果子
+pub const STATUS_ALIVE: i32 = 0;
+/// 蛇存活且吃到了果子
+pub const STATUS_ATE: i32 = 1;
+
+/// 蛇在一个回合中死亡的原因
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DeathCause {
+    /// 方向编号不是 0..=3
+    InvalidAction,
+    /// 蛇头出界
+    OutOfBoard,
+    /// 蛇头撞上某条蛇移动后的身体，两条蛇交换蛇头位置也算
+    Body,
+    /// 两个及以上蛇头进入同一格
+    HeadOn,
+}
+
+impl DeathCause {
+    const ALL: [DeathCause; 4] = [DeathCause::InvalidAction, DeathCause::OutOfBoard, DeathCause::Body, DeathCause::HeadOn];
+
+    /// 导出给 JS 和写进二进制回放的编号，按声明顺序为 0..=3
+    pub fn to_code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn from_code(code: i32) -> Option<DeathCause> {
+        usize::try_from(code).ok().and_then(|i| DeathCause::ALL.get(i)).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeathCause::InvalidAction => "invalid_action",
+            DeathCause::OutOfBoard => "out_of_board",
+            DeathCause::Body => "body",
+            DeathCause::HeadOn => "head_on",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DeathCause> {
+        DeathCause::ALL.into_iter().find(|cause| cause.name() == name)
+    }
+}
+
+/// 一个回合的结算结果，布局与 `OutputBuffer` 相同
+///
+/// * `status` - 每条蛇一个状态，取值为 `STATUS_*`
+/// * `snakes` - 每条蛇 8 个 i32，死亡的蛇全部为 -1
+/// * `foods` - 补充后的果子坐标，长度与输入相同（棋盘放不下时可能更短）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TurnOutput {
+    pub status: Vec<i32>,
+    pub snakes: Vec<i32>,
+    pub foods: Vec<i32>,
+}
+
+/// 结算一个回合，参数与预编译模块的 `process_turn` 一致
+///
+/// # 参数
+/// * `n` - 棋盘边长
+/// * `snakes` - 所有存活的蛇，每条 8 个 i32
+/// * `actions` - 每条蛇的方向编号（0 上，1 左，2 下，3 右），非法方向视为死亡
+/// * `foods` - 当前果子坐标
+/// * `seed` - 补充果子用的随机种子
+pub fn process_turn(n: i32, snakes: &[i32], actions: &[i32], foods: &[i32], seed: u64) -> Result<TurnOutput, InputError> {
+    let snakes = parse_snakes(snakes)?;
+    let foods = input::parse_positions("foods", foods, None)?;
+    let directions: Vec<Option<Direction>> = actions.iter().map(|a| Direction::from_code(*a)).collect();
+    let (next, status, foods) = resolve_turn(n, &snakes, &directions, &foods, seed)?;
+
+    let mut flat_snakes = Vec::with_capacity(next.len() * SNAKE_STRIDE);
+    for snake in &next {
+        match snake {
+            Some(snake) => flat_snakes.extend(flatten(snake.get_body())),
+            None => flat_snakes.extend([-1; SNAKE_STRIDE]),
+        }
+    }
+    Ok(TurnOutput { status, snakes: flat_snakes, foods: flatten(&foods) })
+}
+
+/// 生成开局的果子，参数与预编译模块的 `initial_foods` 一致
+///
+/// 棋盘上的空格不足 `required_count` 个时，返回所有能放下的果子
+pub fn initial_foods(n: i32, snakes: &[i32], seed: u64, required_count: i32) -> Result<Vec<i32>, InputError> {
+    let snakes = parse_snakes(snakes)?;
+    check_range("required_count", required_count, 0, i32::MAX)?;
+    check_board_size(n, snakes.len(), required_count as usize)?;
+    let mut rng = RandomGenerator::new(seed);
+    let mut foods = Vec::new();
+    refill_foods(n, &snakes, &mut foods, required_count as usize, &mut rng);
+    Ok(flatten(&foods))
+}
+
+/// `resolve_turn` 的结果：移动后的蛇（死亡的为 `None`）、每条蛇的状态和补充后的果子
+pub type ResolvedTurn = (Vec<Option<Snake>>, Vec<i32>, Vec<Position>);
+
+/// `process_turn` 的结构化版本
+pub fn resolve_turn(n: i32, snakes: &[Snake], actions: &[Option<Direction>], foods: &[Position], seed: u64) -> Result<ResolvedTurn, InputError> {
+    check_actions(snakes, actions)?;
+    check_board_size(n, snakes.len(), foods.len())?;
+    for snake in snakes {
+        if let Some(pair) = snake.get_body().windows(2).find(|pair| pair[0].manhattan(&pair[1]) != 1) {
+            return Err(InputError::NotAdjacent { field: "snakes", first: pair[0], second: pair[1] });
+        }
+    }
+
+    let moved = move_snakes(n, snakes, actions);
+    let mut status: Vec<i32> = moved.iter()
+        .map(|snake| if snake.is_some() { STATUS_ALIVE } else { STATUS_DEAD })
+        .collect();
+
+    let mut next_foods = foods.to_vec();
+    for (i, snake) in moved.iter().enumerate() {
+        if status[i] == STATUS_DEAD {
+            continue;
+        }
+        let head = snake.as_ref().map(|snake| *snake.get_head());
+        if let Some(index) = next_foods.iter().position(|food| Some(*food) == head) {
+            next_foods.remove(index);
+            status[i] = STATUS_ATE;
+        }
+    }
+
+    let alive: Vec<Snake> = moved.iter().flatten().cloned().collect();
+    let mut rng = RandomGenerator::new(mix_seed(seed, &alive, &next_foods));
+    refill_foods(n, &alive, &mut next_foods, foods.len(), &mut rng);
+
+    Ok((moved, status, next_foods))
+}
+
+/// 所有蛇同时移动并判定死亡，返回移动后的蛇，死亡的为 `None`；不处理果子
+///
+/// 不检查蛇身是否连续，调用方需要保证输入合法
+pub fn move_snakes(n: i32, snakes: &[Snake], actions: &[Option<Direction>]) -> Vec<Option<Snake>> {
+    let causes = death_causes(n, snakes, actions);
+    snakes.iter().zip(actions).zip(causes)
+        .map(|((snake, action), cause)| match (action, cause) {
+            (Some(direction), None) => Some(snake.moved(*direction)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 与 `move_snakes` 相同的判定，给出每条蛇的死亡原因，存活的为 `None`
+///
+/// 同时满足几条时按 `InvalidAction`、`OutOfBoard`、`HeadOn`、`Body` 的顺序取第一条；
+/// `actions` 比 `snakes` 短时多出来的蛇不做判定，导出前先用 `check_actions` 检查
+pub fn death_causes(n: i32, snakes: &[Snake], actions: &[Option<Direction>]) -> Vec<Option<DeathCause>> {
+    // 所有蛇同时移动，非法方向直接判死
+    let moved: Vec<Option<Snake>> = snakes.iter().zip(actions)
+        .map(|(snake, action)| action.map(|direction| snake.moved(direction)))
+        .collect();
+
+    moved.iter().enumerate()
+        .map(|(i, snake)| {
+            let Some(snake) = snake else {
+                return Some(DeathCause::InvalidAction);
+            };
+            let head = snake.get_head();
+            let out_of_board = head.get_x() < 1 || head.get_x() > n || head.get_y() < 1 || head.get_y() > n;
+            let hit_body = moved.iter().flatten().any(|other| other.get_body()[1..].contains(head));
+            let head_on = moved.iter().enumerate()
+                .any(|(j, other)| j != i && other.as_ref().is_some_and(|other| other.get_head() == head));
+            if out_of_board {
+                Some(DeathCause::OutOfBoard)
+            } else if head_on {
+                Some(DeathCause::HeadOn)
+            } else if hit_body {
+                Some(DeathCause::Body)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 在空格中随机补充果子，直到数量达到 `count` 或者棋盘没有空格
+fn refill_foods(n: i32, snakes: &[Snake], foods: &mut Vec<Position>, count: usize, rng: &mut RandomGenerator) {
+    let mut empty: Vec<Position> = Vec::new();
+    for x in 1..=n {
+        for y in 1..=n {
+            let pos = Position::new(x, y);
+            if !foods.contains(&pos) && !snakes.iter().any(|snake| snake.get_body().contains(&pos)) {
+                empty.push(pos);
+            }
+        }
+    }
+
+    while foods.len() < count && !empty.is_empty() {
+        let index = rng.generate_int(0, empty.len() as i32 - 1) as usize;
+        foods.push(empty.swap_remove(index));
+    }
+}
+
+/// 每条蛇恰好有一个方向编号
+pub fn check_actions(snakes: &[Snake], actions: &[Option<Direction>]) -> Result<(), InputError> {
+    if snakes.len() != actions.len() {
+        return Err(InputError::WrongLength { field: "actions", expected: snakes.len(), actual: actions.len() });
+    }
+    Ok(())
+}
+
+/// 与预编译模块一致，棋盘格数不能少于蛇身格数的两倍，也不能少于果子数的两倍；
+/// 不满足时按能放下的最小边长报 `OutOfRange`
+fn check_board_size(n: i32, snake_count: usize, food_count: usize) -> Result<(), InputError> {
+    let required = (2 * SNAKE_LENGTH * snake_count).max(2 * food_count);
+    let min = (1..).find(|side: &usize| side * side >= required).unwrap_or(0);
+    check_range("board_size", n, i32::try_from(min).unwrap_or(i32::MAX), i32::MAX)
+}
+
+/// 每回合调用方传入的种子相同，把局面混进去，避免每回合都在同一个位置补果子
+fn mix_seed(seed: u64, snakes: &[Snake], foods: &[Position]) -> u64 {
+    let mut hash = seed ^ 0xcbf29ce484222325;
+    let positions = snakes.iter().flat_map(|snake| snake.get_body()).chain(foods);
+    for pos in positions {
+        for v in [pos.get_x(), pos.get_y()] {
+            hash ^= v as u32 as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// 每条蛇 `SNAKE_STRIDE` 个 i32；只检查数组长度，蛇身是否连续由 `resolve_turn` 检查
+pub fn parse_snakes(input: &[i32]) -> Result<Vec<Snake>, InputError> {
+    if !input.len().is_multiple_of(SNAKE_STRIDE) {
+        return Err(InputError::WrongLength { field: "snakes", expected: input.len().next_multiple_of(SNAKE_STRIDE), actual: input.len() });
+    }
+    Ok(input.chunks(SNAKE_STRIDE).map(|chunk| Snake::from_body(chunk.chunks_exact(2).map(|pair| Position::new(pair[0], pair[1])).collect())).collect())
+}
+
+fn flatten(positions: &[Position]) -> Vec<i32> {
+    positions.iter().flat_map(|pos| [pos.get_x(), pos.get_y()]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOODS: [i32; 10] = [5, 5, 4, 4, 3, 1, 4, 3, 5, 1];
+
+    fn food_set(foods: &[i32]) -> Vec<(i32, i32)> {
+        let mut set: Vec<(i32, i32)> = foods.chunks(2).map(|p| (p[0], p[1])).collect();
+        set.sort();
+        set
+    }
+
+    #[test]
+    fn test_move_and_eat() {
+        let snakes = [1, 4, 1, 3, 1, 2, 1, 1, 5, 2, 5, 3, 5, 4, 5, 5];
+        let foods = [1, 5, 3, 3, 4, 4, 2, 2, 3, 1];
+        let output = process_turn(5, &snakes, &[0, 2], &foods, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_ATE, STATUS_ALIVE]);
+        assert_eq!(output.snakes, vec![1, 5, 1, 4, 1, 3, 1, 2, 5, 1, 5, 2, 5, 3, 5, 4]);
+        assert_eq!(output.foods.len(), foods.len());
+        let after = food_set(&output.foods);
+        assert!(!after.contains(&(1, 5)));
+        for cell in output.snakes.chunks(2) {
+            assert!(!after.contains(&(cell[0], cell[1])));
+        }
+    }
+
+    #[test]
+    fn test_head_on_on_food() {
+        // 两蛇争食 (2,3)，碰撞优先，两蛇都死且果子还在
+        let snakes = [1, 3, 1, 2, 1, 1, 2, 1, 3, 3, 4, 3, 5, 3, 5, 4];
+        let foods = [2, 3, 5, 5, 4, 4, 2, 2, 3, 1];
+        let output = process_turn(5, &snakes, &[3, 1], &foods, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD, STATUS_DEAD]);
+        assert_eq!(output.snakes, vec![-1; 16]);
+        assert_eq!(food_set(&output.foods), food_set(&foods));
+    }
+
+    #[test]
+    fn test_three_heads_meet() {
+        let snakes = [2, 3, 1, 3, 1, 2, 1, 1, 3, 4, 4, 4, 5, 4, 5, 5, 4, 3, 5, 3, 5, 2, 5, 1];
+        let output = process_turn(5, &snakes, &[3, 2, 1], &[3, 3, 5, 5, 4, 1, 2, 2, 3, 1], 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD; 3]);
+    }
+
+    #[test]
+    fn test_head_swap() {
+        let snakes = [1, 3, 1, 2, 1, 1, 2, 1, 2, 3, 3, 3, 4, 3, 5, 3];
+        let output = process_turn(5, &snakes, &[3, 1], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD, STATUS_DEAD]);
+    }
+
+    #[test]
+    fn test_tails_move_away() {
+        // 钻进自己的尾巴和别的蛇的尾巴都是安全的
+        let snakes = [1, 1, 1, 2, 2, 2, 2, 1, 3, 4, 3, 3, 3, 2, 4, 2];
+        let output = process_turn(5, &snakes, &[3, 0], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_ALIVE, STATUS_ALIVE]);
+
+        let snakes = [1, 2, 1, 3, 1, 4, 1, 5, 3, 4, 3, 3, 3, 2, 2, 2];
+        let output = process_turn(5, &snakes, &[3, 0], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_ALIVE, STATUS_ALIVE]);
+        assert_eq!(&output.snakes[0..2], &[2, 2]);
+    }
+
+    #[test]
+    fn test_body_of_dying_snake_still_kills() {
+        // 第二条蛇撞墙，第一条蛇撞上它移动后的身体，同样死亡
+        let snakes = [1, 4, 1, 3, 1, 2, 1, 1, 2, 5, 2, 4, 3, 4, 4, 4];
+        let output = process_turn(5, &snakes, &[3, 0], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD, STATUS_DEAD]);
+    }
+
+    #[test]
+    fn test_neck_wall_and_invalid_action() {
+        let snakes = [1, 3, 1, 2, 1, 1, 2, 1, 5, 5, 5, 4, 5, 3, 5, 2];
+        let output = process_turn(5, &snakes, &[2, 3], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD, STATUS_DEAD]);
+
+        let output = process_turn(5, &snakes, &[7, 1], &FOODS, 7).unwrap();
+        assert_eq!(output.status, vec![STATUS_DEAD, STATUS_ALIVE]);
+    }
+
+    #[test]
+    fn test_death_causes() {
+        let snakes = parse_snakes(&[1, 3, 1, 2, 1, 1, 2, 1, 3, 3, 4, 3, 5, 3, 5, 4]).unwrap();
+        let causes = death_causes(5, &snakes, &[Some(Direction::Right), Some(Direction::Left)]);
+        assert_eq!(causes, vec![Some(DeathCause::HeadOn); 2]);
+        let causes = death_causes(5, &snakes, &[Some(Direction::Left), None]);
+        assert_eq!(causes, vec![Some(DeathCause::OutOfBoard), Some(DeathCause::InvalidAction)]);
+        let causes = death_causes(5, &snakes, &[Some(Direction::Down), Some(Direction::Up)]);
+        assert_eq!(causes, vec![Some(DeathCause::Body), None]);
+        assert_eq!(DeathCause::from_name("head_on"), Some(DeathCause::HeadOn));
+        assert_eq!(DeathCause::from_code(DeathCause::Body.to_code()), Some(DeathCause::Body));
+        assert_eq!(DeathCause::from_code(4), None);
+    }
+
+    #[test]
+    fn test_refill_is_deterministic() {
+        let snakes = [1, 4, 1, 3, 1, 2, 1, 1, 5, 2, 5, 3, 5, 4, 5, 5];
+        let foods = [1, 5, 3, 3, 4, 4, 2, 2, 3, 1];
+        let a = process_turn(5, &snakes, &[0, 2], &foods, 7).unwrap();
+        let b = process_turn(5, &snakes, &[0, 2], &foods, 7).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_initial_foods() {
+        let snakes = [1, 4, 1, 3, 1, 2, 1, 1, 5, 2, 5, 3, 5, 4, 5, 5];
+        let foods = initial_foods(5, &snakes, 1, 5).unwrap();
+        assert_eq!(foods.len(), 10);
+        let set = food_set(&foods);
+        assert!(set.windows(2).all(|pair| pair[0] != pair[1]));
+        for cell in snakes.chunks(2) {
+            assert!(!set.contains(&(cell[0], cell[1])));
+        }
+
+        // 3x3 的棋盘只剩 5 个空格
+        let foods = initial_foods(3, &[1, 1, 1, 2, 1, 3, 2, 3], 3, 4).unwrap();
+        assert_eq!(food_set(&foods).len(), 4);
+    }
+
+    #[test]
+    fn test_bad_input() {
+        let snake = [1, 4, 1, 3, 1, 2, 1, 1];
+        assert_eq!(process_turn(5, &[3, 4, 3, 3, 2, 3, 2, 3], &[0], &FOODS, 7),
+            Err(InputError::NotAdjacent { field: "snakes", first: Position::new(2, 3), second: Position::new(2, 3) }));
+        assert_eq!(process_turn(5, &snake[..6], &[0], &FOODS, 7),
+            Err(InputError::WrongLength { field: "snakes", expected: 8, actual: 6 }));
+        assert_eq!(process_turn(5, &snake, &[0, 1], &FOODS, 7),
+            Err(InputError::WrongLength { field: "actions", expected: 1, actual: 2 }));
+        assert_eq!(process_turn(5, &snake, &[0], &FOODS[..9], 7),
+            Err(InputError::WrongLength { field: "foods", expected: 10, actual: 9 }));
+        // 一条蛇至少要 8 格，5 个果子至少要 10 格
+        assert_eq!(process_turn(2, &[1, 1, 1, 2, 2, 2, 2, 1], &[0], &[], 7),
+            Err(InputError::OutOfRange { field: "board_size", value: 2, min: 3, max: i32::MAX }));
+        assert_eq!(initial_foods(3, &[], 1, 5),
+            Err(InputError::OutOfRange { field: "board_size", value: 3, min: 4, max: i32::MAX }));
+        assert_eq!(initial_foods(5, &snake, 1, -1),
+            Err(InputError::OutOfRange { field: "required_count", value: -1, min: 0, max: i32::MAX }));
+    }
+}
@@ -0,0 +1,308 @@
+//! 多蛇对局的完整流程，与 `T3/snake-engine.js` 保持一致
+//!
+//! 每回合先为每条存活的蛇生成它看到的局面（`Observation`），收集所有蛇的动作后
+//! 交给 `board::resolve_turn` 统一结算，再更新得分和死亡回合。
+
+use crate::board::{self, DeathCause, STATUS_ATE, STATUS_DEAD};
+use crate::map::{Direction, Position, Snake};
+
+/// 决策超时上限（毫秒），超时的蛇沿原方向前进
+pub const TIME_LIMIT_MS: f64 = 500.0;
+
+/// 对局参数，与 `T3/game-config.js` 中的 `gameParameters` 对应
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    pub board_size: i32,
+    pub snake_count: usize,
+    pub food_count: usize,
+    pub max_rounds: i32,
+    pub initial_snakes: Vec<[i32; 8]>,
+}
+
+impl GameConfig {
+    pub fn one_vs_one() -> GameConfig {
+        GameConfig {
+            board_size: 5,
+            snake_count: 2,
+            food_count: 5,
+            max_rounds: 50,
+            initial_snakes: vec![
+                [1, 4, 1, 3, 1, 2, 1, 1],
+                [5, 2, 5, 3, 5, 4, 5, 5],
+            ],
+        }
+    }
+
+    pub fn four_snakes() -> GameConfig {
+        GameConfig {
+            board_size: 8,
+            snake_count: 4,
+            food_count: 10,
+            max_rounds: 100,
+            initial_snakes: vec![
+                [4, 1, 3, 1, 2, 1, 1, 1],
+                [8, 4, 8, 3, 8, 2, 8, 1],
+                [5, 8, 6, 8, 7, 8, 8, 8],
+                [1, 5, 1, 6, 1, 7, 1, 8],
+            ],
+        }
+    }
+
+    pub fn custom() -> GameConfig {
+        GameConfig {
+            board_size: 12,
+            snake_count: 8,
+            food_count: 20,
+            max_rounds: 200,
+            initial_snakes: vec![
+                [2, 2, 2, 3, 3, 3, 4, 3],
+                [11, 2, 11, 3, 10, 3, 9, 3],
+                [2, 11, 2, 10, 3, 10, 4, 10],
+                [11, 11, 11, 10, 10, 10, 9, 10],
+                [2, 6, 3, 6, 4, 6, 5, 6],
+                [11, 6, 10, 6, 9, 6, 8, 6],
+                [6, 2, 6, 3, 6, 4, 6, 5],
+                [6, 11, 6, 10, 6, 9, 6, 8],
+            ],
+        }
+    }
+
+    /// 按 `GAME_MODE` 的名字取参数："1v1"、"4snakes" 或 "custom"
+    pub fn from_mode(mode: &str) -> Option<GameConfig> {
+        match mode {
+            "1v1" => Some(GameConfig::one_vs_one()),
+            "4snakes" => Some(GameConfig::four_snakes()),
+            "custom" => Some(GameConfig::custom()),
+            _ => None,
+        }
+    }
+}
+
+/// 某条蛇在一个回合中看到的局面，字段与 `greedy_snake_step` 的参数一一对应
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observation {
+    pub board_size: i32,
+    pub my_snake: Vec<i32>,
+    pub snake_num: i32,
+    pub other_snakes: Vec<i32>,
+    pub food_num: i32,
+    pub foods: Vec<i32>,
+    pub round: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct GameState {
+    config: GameConfig,
+    seed: u64,
+    /// 按座位编号，死亡的蛇为 `None`
+    snakes: Vec<Option<Snake>>,
+    foods: Vec<Position>,
+    scores: Vec<i32>,
+    /// 死亡的回合，存活到最后的蛇为 `max_rounds`
+    dead_round: Vec<i32>,
+    round: i32,
+    /// 上一回合死亡的座位和原因
+    last_deaths: Vec<(usize, DeathCause)>,
+    /// 上一回合补充的果子
+    last_spawns: Vec<Position>,
+}
+
+impl GameState {
+
+    /// 开局，`config.snake_count` 不能超过初始位置的数量，棋盘要放得下所有蛇和果子（见 `Replay::validate`），否则 panic
+    pub fn new(config: GameConfig, seed: u64) -> GameState {
+        // 只避开真正上场的蛇，没用到的初始位置上也可以放果子
+        let all_snakes: Vec<i32> = config.initial_snakes.iter().take(config.snake_count).flatten().copied().collect();
+        let foods = board::initial_foods(config.board_size, &all_snakes, seed, config.food_count as i32)
+            .expect("invalid game config")
+            .chunks(2)
+            .map(|pair| Position::new(pair[0], pair[1]))
+            .collect();
+        let snakes = config.initial_snakes.iter()
+            .take(config.snake_count)
+            .map(|coords| Some(Snake::from_body(coords.chunks(2).map(|pair| Position::new(pair[0], pair[1])).collect())))
+            .collect();
+        let count = config.snake_count;
+        GameState {
+            seed,
+            snakes,
+            foods,
+            scores: vec![0; count],
+            dead_round: vec![config.max_rounds; count],
+            round: 0,
+            last_deaths: vec![],
+            last_spawns: vec![],
+            config,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.round >= self.config.max_rounds || self.snakes.iter().all(Option::is_none)
+    }
+
+    /// 包含本回合在内的剩余回合数
+    pub fn remaining_rounds(&self) -> i32 {
+        self.config.max_rounds - self.round
+    }
+
+    /// 当前存活的座位编号，顺序即 `step` 接收动作的顺序
+    pub fn alive_seats(&self) -> Vec<usize> {
+        (0..self.snakes.len()).filter(|seat| self.snakes[*seat].is_some()).collect()
+    }
+
+    /// 座位 `seat` 上的蛇看到的局面，蛇已死亡时返回 `None`
+    pub fn observation(&self, seat: usize) -> Option<Observation> {
+        let me = self.snakes.get(seat)?.as_ref()?;
+        let others: Vec<&Snake> = self.snakes.iter().enumerate()
+            .filter(|(other, _)| *other != seat)
+            .filter_map(|(_, snake)| snake.as_ref())
+            .collect();
+        Some(Observation {
+            board_size: self.config.board_size,
+            my_snake: flatten(me.get_body()),
+            snake_num: others.len() as i32,
+            other_snakes: others.iter().flat_map(|snake| flatten(snake.get_body())).collect(),
+            food_num: self.foods.len() as i32,
+            foods: flatten(&self.foods),
+            round: self.remaining_rounds(),
+        })
+    }
+
+    /// 超时时代替蛇做出的动作：沿蛇头当前朝向前进
+    pub fn fallback_action(&self, seat: usize) -> i32 {
+        let Some(snake) = &self.snakes[seat] else {
+            return Direction::Up.to_code();
+        };
+        let head = snake.get_head();
+        let neck = &snake.get_body()[1];
+        let direction = Direction::iter().find(|d| &neck.step(*d) == head).unwrap_or(Direction::Up);
+        direction.to_code()
+    }
+
+    /// 结算一个回合，`actions` 按 `alive_seats` 的顺序给出，个数与存活的蛇不同时 panic
+    pub fn step(&mut self, actions: &[i32]) {
+        let seats = self.alive_seats();
+        let snakes: Vec<Snake> = seats.iter().map(|seat| self.snakes[*seat].clone().unwrap()).collect();
+        let directions: Vec<Option<Direction>> = actions.iter().map(|a| Direction::from_code(*a)).collect();
+        let (next, status, foods) = board::resolve_turn(self.config.board_size, &snakes, &directions, &self.foods, self.seed)
+            .expect("one action per alive snake");
+        let causes = board::death_causes(self.config.board_size, &snakes, &directions);
+
+        self.last_deaths.clear();
+        for (((seat, snake), status), cause) in seats.into_iter().zip(next).zip(status).zip(causes) {
+            if status == STATUS_DEAD {
+                self.dead_round[seat] = self.round + 1;
+                self.last_deaths.push((seat, cause.unwrap_or(DeathCause::Body)));
+            } else if status == STATUS_ATE {
+                self.scores[seat] += 1;
+            }
+            self.snakes[seat] = snake;
+        }
+        // 被吃掉的果子所在的格子上是蛇头，新果子不会补在那里，所以原来没有的就是新补的
+        self.last_spawns = foods.iter().copied().filter(|food| !self.foods.contains(food)).collect();
+        self.foods = foods;
+        self.round += 1;
+    }
+
+    pub fn get_config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_round(&self) -> i32 {
+        self.round
+    }
+
+    pub fn get_snakes(&self) -> &Vec<Option<Snake>> {
+        &self.snakes
+    }
+
+    pub fn get_foods(&self) -> &Vec<Position> {
+        &self.foods
+    }
+
+    pub fn get_scores(&self) -> &Vec<i32> {
+        &self.scores
+    }
+
+    pub fn get_dead_round(&self) -> &Vec<i32> {
+        &self.dead_round
+    }
+
+    pub fn is_alive(&self, seat: usize) -> bool {
+        self.snakes[seat].is_some()
+    }
+
+    /// 上一次 `step` 中死亡的座位和原因
+    pub fn get_last_deaths(&self) -> &Vec<(usize, DeathCause)> {
+        &self.last_deaths
+    }
+
+    /// 上一次 `step` 补充的果子
+    pub fn get_last_spawns(&self) -> &Vec<Position> {
+        &self.last_spawns
+    }
+
+}
+
+fn flatten(positions: &[Position]) -> Vec<i32> {
+    positions.iter().flat_map(|pos| [pos.get_x(), pos.get_y()]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observation_matches_engine() {
+        let game = GameState::new(GameConfig::one_vs_one(), 1);
+        let obs = game.observation(1).unwrap();
+        assert_eq!(obs.my_snake, vec![5, 2, 5, 3, 5, 4, 5, 5]);
+        assert_eq!(obs.snake_num, 1);
+        assert_eq!(obs.other_snakes, vec![1, 4, 1, 3, 1, 2, 1, 1]);
+        assert_eq!(obs.food_num, 5);
+        assert_eq!(obs.round, 50);
+    }
+
+    #[test]
+    fn test_initial_foods_ignore_unused_seats() {
+        let mut config = GameConfig::four_snakes();
+        config.snake_count = 2;
+        let seated: Vec<i32> = config.initial_snakes[..2].iter().flatten().copied().collect();
+        let expected: Vec<Position> = board::initial_foods(config.board_size, &seated, 3, config.food_count as i32)
+            .unwrap()
+            .chunks(2)
+            .map(|pair| Position::new(pair[0], pair[1]))
+            .collect();
+        assert_eq!(GameState::new(config, 3).get_foods(), &expected);
+    }
+
+    #[test]
+    fn test_death_and_game_over() {
+        let mut game = GameState::new(GameConfig::one_vs_one(), 1);
+        assert_eq!(game.fallback_action(0), Direction::Up.to_code());
+        assert_eq!(game.fallback_action(1), Direction::Down.to_code());
+        // 两条蛇都掉头撞上自己的脖子
+        game.step(&[2, 0]);
+        assert!(game.is_over());
+        assert_eq!(game.get_dead_round(), &vec![1, 1]);
+        assert_eq!(game.get_last_deaths(), &vec![(0, DeathCause::Body), (1, DeathCause::Body)]);
+        assert!(game.observation(0).is_none());
+    }
+
+    #[test]
+    fn test_game_ends_at_max_rounds() {
+        let mut config = GameConfig::one_vs_one();
+        config.max_rounds = 2;
+        let mut game = GameState::new(config, 1);
+        game.step(&[3, 1]);
+        assert_eq!(game.remaining_rounds(), 1);
+        assert_eq!(game.observation(0).unwrap().round, 1);
+        game.step(&[3, 1]);
+        assert!(game.is_over());
+        assert_eq!(game.get_dead_round(), &vec![2, 2]);
+    }
+}
@@ -0,0 +1,128 @@
+//! wasm 接口的输入解析与检查
+//!
+//! 输入不合法时各个导出函数不再 panic（在 wasm 中会变成无法定位的 `unreachable`），
+//! 而是返回 `InputError::code` 给出的负数，与表示“无路可走”的 -1 区分开。
+
+use crate::map::{Position, Snake};
+use std::collections::HashSet;
+use std::fmt;
+
+/// 输入不合法的原因
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// 数组长度不对；数组长度为奇数时，`expected` 为补齐最后一个坐标后的长度
+    WrongLength { field: &'static str, expected: usize, actual: usize },
+    /// 蛇身相邻两节不在上下左右相邻的格子里
+    NotAdjacent { field: &'static str, first: Position, second: Position },
+    /// 坐标在棋盘外
+    OutOfBoard { field: &'static str, pos: Position },
+    /// 两个实体（蛇身、果子、障碍物）占了同一个格子
+    Overlap { pos: Position },
+    /// 数值超出支持的范围，比如棋盘边长或蛇的数量
+    OutOfRange { field: &'static str, value: i32, min: i32, max: i32 },
+}
+
+impl InputError {
+    /// 导出函数返回的错误码，-1 留给“找不到路径”
+    pub fn code(&self) -> i32 {
+        match self {
+            InputError::WrongLength { .. } => -2,
+            InputError::NotAdjacent { .. } => -3,
+            InputError::OutOfBoard { .. } => -4,
+            InputError::Overlap { .. } => -5,
+            InputError::OutOfRange { .. } => -6,
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::WrongLength { field, expected, actual } =>
+                write!(f, "`{field}` should have {expected} numbers but has {actual}"),
+            InputError::NotAdjacent { field, first, second } =>
+                write!(f, "`{field}` segments ({}, {}) and ({}, {}) are not adjacent", first.get_x(), first.get_y(), second.get_x(), second.get_y()),
+            InputError::OutOfBoard { field, pos } =>
+                write!(f, "`{field}` has ({}, {}) outside the board", pos.get_x(), pos.get_y()),
+            InputError::Overlap { pos } =>
+                write!(f, "more than one entity at ({}, {})", pos.get_x(), pos.get_y()),
+            InputError::OutOfRange { field, value, min, max } =>
+                write!(f, "`{field}` is {value}, expected {min}..={max}"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// 检查 `value` 在 `min..=max` 之内
+pub fn check_range(field: &'static str, value: i32, min: i32, max: i32) -> Result<(), InputError> {
+    if value < min || value > max {
+        return Err(InputError::OutOfRange { field, value, min, max });
+    }
+    Ok(())
+}
+
+/// 把坐标数组解析成位置，`count` 给出时要求恰好有这么多个坐标
+pub fn parse_positions(field: &'static str, input: &[i32], count: Option<usize>) -> Result<Vec<Position>, InputError> {
+    let expected = count.map_or(input.len() + input.len() % 2, |count| count * 2);
+    if input.len() != expected {
+        return Err(InputError::WrongLength { field, expected, actual: input.len() });
+    }
+    Ok(input.chunks_exact(2).map(|pair| Position::new(pair[0], pair[1])).collect())
+}
+
+/// 解析一条蛇并检查每节都在棋盘内、相邻两节相邻；`length` 给出时要求恰好有这么多节
+pub fn parse_snake(field: &'static str, input: &[i32], length: Option<usize>, board_size: i32) -> Result<Snake, InputError> {
+    let body = parse_positions(field, input, length)?;
+    if body.is_empty() {
+        return Err(InputError::WrongLength { field, expected: 2, actual: 0 });
+    }
+    check_in_board(field, &body, board_size)?;
+    if let Some(pair) = body.windows(2).find(|pair| pair[0].manhattan(&pair[1]) != 1) {
+        return Err(InputError::NotAdjacent { field, first: pair[0], second: pair[1] });
+    }
+    Ok(Snake::from_body(body))
+}
+
+pub fn check_in_board(field: &'static str, positions: &[Position], board_size: i32) -> Result<(), InputError> {
+    match positions.iter().find(|pos| pos.get_x() < 1 || pos.get_x() > board_size || pos.get_y() < 1 || pos.get_y() > board_size) {
+        Some(pos) => Err(InputError::OutOfBoard { field, pos: *pos }),
+        None => Ok(()),
+    }
+}
+
+/// 检查所有实体两两不重叠，包括同一条蛇的不同节
+pub fn check_no_overlap<'a>(groups: impl IntoIterator<Item = &'a [Position]>) -> Result<(), InputError> {
+    let mut seen = HashSet::new();
+    for pos in groups.into_iter().flatten() {
+        if !seen.insert(*pos) {
+            return Err(InputError::Overlap { pos: *pos });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snake() {
+        assert!(parse_snake("snake", &[1, 1, 1, 2, 2, 2, 2, 1], Some(4), 8).is_ok());
+        assert_eq!(parse_snake("snake", &[1, 1, 1, 2, 2, 2], Some(4), 8).unwrap_err(),
+            InputError::WrongLength { field: "snake", expected: 8, actual: 6 });
+        assert_eq!(parse_snake("snake", &[1, 1, 1, 2, 2], None, 8).unwrap_err(),
+            InputError::WrongLength { field: "snake", expected: 6, actual: 5 });
+        assert_eq!(parse_snake("snake", &[1, 1, 1, 3], None, 8).unwrap_err(),
+            InputError::NotAdjacent { field: "snake", first: Position::new(1, 1), second: Position::new(1, 3) });
+        assert_eq!(parse_snake("snake", &[8, 8, 9, 8], None, 8).unwrap_err().code(), -4);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let snake = [Position::new(1, 1), Position::new(1, 2)];
+        let foods = [Position::new(3, 3), Position::new(1, 2)];
+        assert_eq!(check_no_overlap([&snake[..], &foods[..]]), Err(InputError::Overlap { pos: Position::new(1, 2) }));
+        assert!(check_no_overlap([&snake[..], &foods[..1]]).is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+//! 回放文件和决策记录用的最小 JSON 实现，snake_core 不依赖第三方库
+//!
+//! 数字一律按 `f64` 解析，超出 2^53 的整数（比如随机种子）需要写成字符串。
+
+use std::fmt::Write;
+
+/// 数组和对象最多嵌套的层数，回放只用到 4 层；更深的输入直接报错，避免递归解析时栈溢出
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// 保留字段的书写顺序
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// 只接受没有小数部分、在 `i64` 范围内的数字
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_f64().filter(|v| v.fract() == 0.0 && v.abs() < 9.007_199_254_740_992e15).map(|v| v as i64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// 紧凑格式，不带多余的空白
+    pub fn write(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            // JSON 不能表示 NaN 和无穷大
+            Json::Number(v) if !v.is_finite() => out.push_str("null"),
+            Json::Number(v) => write!(out, "{v}").unwrap(),
+            Json::String(s) => write_string(s, out),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(name, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    /// 解析一个完整的 JSON 文本，出错时返回出错的字节位置和原因
+    pub fn parse(text: &str) -> Result<Json, (usize, &'static str)> {
+        let mut parser = Parser { bytes: text.as_bytes(), pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err((parser.pos, "trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// 当前所在的数组和对象的层数
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: Json) -> Result<Json, (usize, &'static str)> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err((self.pos, "unexpected character"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, (usize, &'static str)> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            None => Err((self.pos, "unexpected end of input")),
+            Some(b'n') => self.expect("null", Json::Null),
+            Some(b't') => self.expect("true", Json::Bool(true)),
+            Some(b'f') => self.expect("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[' | b'{') => {
+                if self.depth == MAX_DEPTH {
+                    return Err((self.pos, "too deeply nested"));
+                }
+                self.depth += 1;
+                let value = if self.bytes[self.pos] == b'[' { self.array() } else { self.object() };
+                self.depth -= 1;
+                value
+            }
+            Some(_) => self.number(),
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, (usize, &'static str)> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err((self.pos, "expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, (usize, &'static str)> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b'"') {
+                return Err((self.pos, "expected a string key"));
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b':') {
+                return Err((self.pos, "expected `:`"));
+            }
+            self.pos += 1;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err((self.pos, "expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, (usize, &'static str)> {
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b)) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .map(Json::Number)
+            .ok_or((start, "invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, (usize, &'static str)> {
+        // 跳过开头的引号
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while self.bytes.get(self.pos).is_some_and(|b| *b != b'"' && *b != b'\\') {
+                self.pos += 1;
+            }
+            // 输入来自 &str，引号和反斜杠都不会落在多字节字符中间
+            out.push_str(std::str::from_utf8(&self.bytes[start..self.pos]).unwrap());
+            match self.bytes.get(self.pos) {
+                None => return Err((self.pos, "unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(_) => {
+                    let escape = *self.bytes.get(self.pos + 1).ok_or((self.pos, "unterminated string"))?;
+                    self.pos += 2;
+                    match escape {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let hex = self.bytes.get(self.pos..self.pos + 4).ok_or((self.pos, "invalid escape"))?;
+                            let code = std::str::from_utf8(hex).ok()
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or((self.pos, "invalid escape"))?;
+                            self.pos += 4;
+                            // 不处理代理对，回放文件里只会出现控制字符的转义
+                            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err((self.pos - 1, "invalid escape")),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = r#"{"a":[1,-2.5,true,null],"b":"x\"y\n\u0001","c":{}}"#;
+        let value = Json::parse(text).unwrap();
+        assert_eq!(value.get("a").unwrap().as_array().unwrap()[1].as_f64(), Some(-2.5));
+        assert_eq!(value.get("b").unwrap().as_str(), Some("x\"y\n\u{1}"));
+        let mut out = String::new();
+        value.write(&mut out);
+        assert_eq!(out, text);
+
+        assert_eq!(Json::parse(" [ 1 , 2 ] ").unwrap(), Json::Array(vec![Json::Number(1.0), Json::Number(2.0)]));
+        assert_eq!(Json::parse("[1,]").unwrap_err().0, 3);
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1] x").is_err());
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(Json::parse(&nested(MAX_DEPTH + 1)), Err((MAX_DEPTH, "too deeply nested")));
+        // 以前会在递归中栈溢出，整个进程退出
+        assert_eq!(Json::parse(&"[".repeat(1_000_000)), Err((MAX_DEPTH, "too deeply nested")));
+        assert_eq!(Json::parse(&"{\"a\":".repeat(1_000_000)).unwrap_err().1, "too deeply nested");
+    }
+}
@@ -0,0 +1,21 @@
+//! T1/T2/T3 共用的场地模型与寻路，三个 wasm crate 只负责解析输入和输出方向
+
+pub mod map;
+pub mod astar;
+pub mod bitboard;
+pub mod board;
+pub mod game;
+pub mod input;
+pub mod json;
+pub mod mcts;
+pub mod notation;
+pub mod opponent;
+pub mod random;
+pub mod render;
+pub mod replay;
+pub mod risk;
+pub mod route;
+pub mod search;
+pub mod territory;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// 对手蛇头下一步落在某个格子的概率达到这个值，就把这个格子当成障碍
+pub const HEAD_RISK_THRESHOLD: f64 = 0.1;
+
+#[derive(Debug)]
+pub struct Map {
+    my_snake: Snake,
+    other_snakes: Vec<Snake>,
+    barriers: Vec<Position>,
+    obstacles: Vec<Position>,
+    strict_obstacles: Vec<Position>,
+    foods: Vec<Position>,
+    /// T3 的规则：不贴边的果子格子不算撞上（见 `colision_check_at`），T1/T2 不用
+    foods_passable: bool,
+    size: i32,
+}
+
+impl Map {
+
+    /// 多蛇对局的场地（T3），障碍由其他蛇的身体及其蛇头周围的格子构成
+    ///
+    /// 只看这一回合的局面，不预测对手；按对手模型给出的概率放障碍时使用 `with_head_risks`
+    pub fn new(my_snake: Snake, other_snakes: Vec<Snake>, foods: Vec<Position>, size: i32) -> Map {
+        let risks: Vec<(Position, f64)> = other_snakes.iter()
+            .flat_map(|snake| Direction::iter().map(|direction| (snake.get_head().step(direction), 1.0)))
+            .collect();
+        Map::with_head_risks(my_snake, other_snakes, foods, size, &risks)
+    }
+
+    /// 按 `OpponentModel::head_risks` 给出的概率构造 T3 的场地
+    pub fn with_head_risks(my_snake: Snake, other_snakes: Vec<Snake>, foods: Vec<Position>, size: i32, head_risks: &[(Position, f64)]) -> Map {
+        let obstacles = Map::build_obstacles(&other_snakes, head_risks);
+        let strict_obstacles = Map::build_strict_obstacles(&other_snakes);
+        Map { my_snake, other_snakes, barriers: vec![], obstacles, strict_obstacles, foods, foods_passable: true, size }
+    }
+
+    /// 单蛇的场地（T1/T2），`barriers` 为固定的障碍物，T1 中为空
+    pub fn with_barriers(my_snake: Snake, foods: Vec<Position>, barriers: Vec<Position>, size: i32) -> Map {
+        Map { my_snake, other_snakes: vec![], barriers, obstacles: vec![], strict_obstacles: vec![], foods, foods_passable: false, size }
+    }
+
+    /// 其他蛇除蛇尾外的身体，加上对手蛇头下一步落在那里的概率不低于 `HEAD_RISK_THRESHOLD` 的格子
+    pub fn build_obstacles(snakes: &[Snake], head_risks: &[(Position, f64)]) -> Vec<Position> {
+        let mut obstacles:Vec<Position> = vec![];
+        for snake in snakes {
+            for ob in snake.get_without_tail() {
+                obstacles.push(*ob);
+            }
+        }
+        for (pos, risk) in head_risks {
+            if *risk >= HEAD_RISK_THRESHOLD && !obstacles.contains(pos) {
+                obstacles.push(*pos);
+            }
+        }
+        obstacles
+    }
+
+    pub fn build_strict_obstacles(snakes: &[Snake]) -> Vec<Position> {
+        let mut obstacles:Vec<Position> = vec![];
+        for snake in snakes {
+            for ob in snake.get_without_tail() {
+                obstacles.push(*ob);
+            }
+        }
+
+        obstacles
+    }
+
+    pub fn in_board(&self, pos: &Position) -> bool {
+        pos.get_x() >= 1 && pos.get_x() <= self.size && pos.get_y() >= 1 && pos.get_y() <= self.size
+    }
+
+    pub fn colision_check(&self, pos: &Position) -> bool {
+        self.colision_check_at(pos, 1)
+    }
+
+    /// 蛇头走 `steps` 步到达 `pos` 时是否会撞上
+    ///
+    /// 蛇身不会变长，各条蛇的身体按 `Snake::occupies_after` 逐步让出格子；
+    /// 其他蛇头周围的格子始终视为障碍。T3 的场地（`Map::new`）中不贴边的果子格子不算撞上
+    pub fn colision_check_at(&self, pos: &Position, steps: i32) -> bool {
+        if self.foods_passable && self.foods.contains(pos) && pos.get_x() > 1 && pos.get_y() > 1 && pos.get_x() < self.size && pos.get_y() < self.size {
+            false
+        } else if self.strict_obstacles_check_at(pos, steps) {
+            true
+        } else {
+            self.obstacles.contains(pos) && !self.strict_obstacles.contains(pos)
+        }
+    }
+
+    pub fn strict_obstacles_check(&self, pos: &Position) -> bool {
+        self.strict_obstacles_check_at(pos, 1)
+    }
+
+    /// 与 `colision_check_at` 相同，但不考虑其他蛇头周围的格子
+    pub fn strict_obstacles_check_at(&self, pos: &Position, steps: i32) -> bool {
+        !self.in_board(pos) || self.barriers.contains(pos) || self.bodies_occupy_after(pos, steps)
+    }
+
+    /// 蛇头在第 `steps` 步到达 `start` 之后，还能走到的格子数，数到 `limit` 为止
+    ///
+    /// 自己的身体按步数逐节让出；其他蛇会在新的地方长出身体，把它们的整条身体都当成墙。
+    /// `start` 本身不能通行时返回 0
+    pub fn reachable_area(&self, start: &Position, steps: i32, limit: usize) -> usize {
+        let blocked = |pos: &Position, steps: i32| {
+            !self.in_board(pos)
+                || self.barriers.contains(pos)
+                || self.my_snake.occupies_after(pos, steps)
+                || self.other_snakes.iter().any(|snake| snake.get_body().contains(pos))
+        };
+        if blocked(start, steps) {
+            return 0;
+        }
+
+        let index = |pos: &Position| ((pos.get_x() - 1) * self.size + pos.get_y() - 1) as usize;
+        let mut visited = vec![false; (self.size * self.size) as usize];
+        visited[index(start)] = true;
+        let mut frontier = vec![*start];
+        let mut count = 1;
+        let mut depth = steps;
+        while !frontier.is_empty() && count < limit {
+            depth += 1;
+            let mut next = Vec::new();
+            for pos in &frontier {
+                for direction in Direction::iter() {
+                    let neighbor = pos.step(direction);
+                    if !blocked(&neighbor, depth) && !visited[index(&neighbor)] {
+                        visited[index(&neighbor)] = true;
+                        next.push(neighbor);
+                        count += 1;
+                    }
+                }
+            }
+            frontier = next;
+        }
+        count.min(limit)
+    }
+
+    fn bodies_occupy_after(&self, pos: &Position, steps: i32) -> bool {
+        self.my_snake.occupies_after(pos, steps) || self.other_snakes.iter().any(|snake| snake.occupies_after(pos, steps))
+    }
+
+    pub fn get_my_snake(&self) -> &Snake {
+        &self.my_snake
+    }
+
+    pub fn get_other_snakes(&self) -> &Vec<Snake> {
+        &self.other_snakes
+    }
+
+    pub fn get_barriers(&self) -> &Vec<Position> {
+        &self.barriers
+    }
+
+    pub fn get_foods(&self) -> &Vec<Position> {
+        &self.foods
+    }
+
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    /// 检查局面是否符合规则，返回所有不符合的地方
+    ///
+    /// 蛇按 `Entity::Snake` 编号，我方为 0，其余按 `other_snakes` 的顺序从 1 开始。
+    /// 每条蛇必须非空、每节都在棋盘内且相邻两节相邻；蛇身、障碍物、果子两两不能重叠，
+    /// 同一条蛇的不同节也不能重叠。重叠时 `first` 是先检查到的实体（顺序为蛇、障碍物、果子）
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut occupied: HashMap<Position, Entity> = HashMap::new();
+        let mut entities = Vec::new();
+        let snakes = std::iter::once(&self.my_snake).chain(self.other_snakes.iter());
+        for (i, snake) in snakes.enumerate() {
+            if snake.body.is_empty() {
+                violations.push(Violation::EmptySnake { snake: i });
+            }
+            for pair in snake.body.windows(2).filter(|pair| pair[0].manhattan(&pair[1]) != 1) {
+                violations.push(Violation::NotAdjacent { snake: i, first: pair[0], second: pair[1] });
+            }
+            entities.extend(snake.body.iter().map(|pos| (Entity::Snake(i), *pos)));
+        }
+        entities.extend(self.barriers.iter().map(|pos| (Entity::Barrier, *pos)));
+        entities.extend(self.foods.iter().map(|pos| (Entity::Food, *pos)));
+
+        for (entity, pos) in entities {
+            if !self.in_board(&pos) {
+                violations.push(Violation::OutOfBoard { entity, pos });
+            }
+            match occupied.get(&pos) {
+                Some(first) => violations.push(Violation::Overlap { pos, first: *first, second: entity }),
+                None => {
+                    occupied.insert(pos, entity);
+                }
+            }
+        }
+        violations
+    }
+
+}
+
+/// 场上的一个实体，`Snake` 带蛇的编号
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Entity {
+    Snake(usize),
+    Barrier,
+    Food,
+}
+
+/// `Map::validate` 找到的一处问题
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Violation {
+    /// 蛇没有身体
+    EmptySnake { snake: usize },
+    /// 蛇身相邻两节不在上下左右相邻的格子里
+    NotAdjacent { snake: usize, first: Position, second: Position },
+    OutOfBoard { entity: Entity, pos: Position },
+    /// 两个实体占了同一个格子
+    Overlap { pos: Position, first: Entity, second: Entity },
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entity::Snake(i) => write!(f, "snake {i}"),
+            Entity::Barrier => write!(f, "barrier"),
+            Entity::Food => write!(f, "food"),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::EmptySnake { snake } =>
+                write!(f, "snake {snake} has no body"),
+            Violation::NotAdjacent { snake, first, second } =>
+                write!(f, "snake {snake} segments ({}, {}) and ({}, {}) are not adjacent", first.get_x(), first.get_y(), second.get_x(), second.get_y()),
+            Violation::OutOfBoard { entity, pos } =>
+                write!(f, "{entity} at ({}, {}) is outside the board", pos.get_x(), pos.get_y()),
+            Violation::Overlap { pos, first, second } =>
+                write!(f, "{first} and {second} both at ({}, {})", pos.get_x(), pos.get_y()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snake {
+    body: Vec<Position>,
+}
+
+impl Snake {
+    pub fn new() -> Snake {
+        Snake { body: Vec::new() }
+    }
+
+    pub fn from_body(body: Vec<Position>) -> Snake {
+        Snake { body }
+    }
+
+    pub fn get_head(&self) -> &Position {
+        &self.body[0]
+    }
+
+    pub fn get_tail(&self) -> &Position {
+        &self.body[self.body.len() - 1]
+    }
+
+    pub fn get_body(&self) -> &[Position] {
+        &self.body
+    }
+
+    pub fn get_without_tail(&self) -> &[Position] {
+        &self.body[0..self.body.len() - 1]
+    }
+
+    /// 再走 `steps` 步之后 `pos` 是否还被这条蛇的身体占着
+    ///
+    /// 蛇身长度不变，第 k 节（蛇头为第 0 节）无论蛇往哪里走，都会在 len - k 步后离开
+    pub fn occupies_after(&self, pos: &Position, steps: i32) -> bool {
+        self.body.iter().position(|p| p == pos).is_some_and(|k| (k as i32) + steps < self.body.len() as i32)
+    }
+
+    /// 蛇头朝 `direction` 走一步后的蛇，长度不变
+    pub fn moved(&self, direction: Direction) -> Snake {
+        let mut body = Vec::with_capacity(self.body.len());
+        body.push(self.get_head().step(direction));
+        body.extend_from_slice(self.get_without_tail());
+        Snake { body }
+    }
+
+    /// 相邻两节身体是否都在上下左右相邻的格子里
+    pub fn is_continuous(&self) -> bool {
+        self.body.windows(2).all(|pair| pair[0].manhattan(&pair[1]) == 1)
+    }
+
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Position {
+    x: i32,
+    y: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32) ->Position {
+        Position { x, y }
+    }
+
+    pub fn up(pos: &Position) -> Position {
+        Position {
+            x: pos.x,
+            y: pos.y + 1,
+        }
+    }
+
+    pub fn down(pos: &Position) -> Position {
+        Position {
+            x: pos.x,
+            y: pos.y - 1,
+        }
+    }
+
+    pub fn left(pos: &Position) -> Position {
+        Position {
+            x: pos.x - 1,
+            y: pos.y,
+        }
+    }
+
+    pub fn right(pos: &Position) -> Position {
+        Position {
+            x: pos.x + 1,
+            y: pos.y,
+        }
+    }
+
+    /// 沿 `direction` 移动一格后的位置
+    pub fn step(&self, direction: Direction) -> Position {
+        match direction {
+            Direction::Up => Position::up(self),
+            Direction::Down => Position::down(self),
+            Direction::Left => Position::left(self),
+            Direction::Right => Position::right(self),
+        }
+    }
+
+    pub fn manhattan(&self, other: &Position) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    pub fn get_x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> i32 {
+        self.y
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    Up, Down, Left, Right,
+}
+
+impl Direction {
+    pub fn iter() -> impl Iterator<Item = Direction> {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right].into_iter()
+    }
+
+    /// 评测接口中的方向编号：0 上，1 左，2 下，3 右
+    pub fn to_code(self) -> i32 {
+        match self {
+            Direction::Up => 0,
+            Direction::Left => 1,
+            Direction::Down => 2,
+            Direction::Right => 3,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<Direction> {
+        match code {
+            0 => Some(Direction::Up),
+            1 => Some(Direction::Left),
+            2 => Some(Direction::Down),
+            3 => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    #[test]
+    fn test_reachable_area() {
+        let me = snake(&[1, 4, 1, 5, 2, 5, 3, 5]);
+        let other = snake(&[3, 1, 2, 1, 2, 2, 2, 3]);
+        let map = Map::new(me, vec![other], vec![], 5);
+        // 钻进 (1,3) 只剩 3 格，蛇头原来的位置要第 4 步才让出
+        assert_eq!(map.reachable_area(&Position::new(1, 3), 1, usize::MAX), 3);
+        // 往外走的话，自己的身体都会让出来，除了对手的身体整个棋盘都能走到
+        assert_eq!(map.reachable_area(&Position::new(2, 4), 1, usize::MAX), 25 - 4);
+        assert_eq!(map.reachable_area(&Position::new(2, 4), 1, 5), 5);
+        assert_eq!(map.reachable_area(&Position::new(2, 2), 1, usize::MAX), 0);
+    }
+
+    #[test]
+    fn test_new_blocks_head_neighbours() {
+        // `new` 不预测对手，对手蛇头四周都当成障碍；`with_head_risks` 只挡住概率够高的格子
+        let me = snake(&[1, 1, 1, 2, 1, 3, 1, 4]);
+        let other = snake(&[3, 3, 3, 4, 3, 5, 4, 5]);
+        let map = Map::new(me.clone(), vec![other.clone()], vec![], 5);
+        for pos in [Position::new(2, 3), Position::new(4, 3), Position::new(3, 2)] {
+            assert!(map.colision_check(&pos), "{:?}", pos);
+        }
+        let map = Map::with_head_risks(me, vec![other], vec![], 5, &[(Position::new(2, 3), 0.5), (Position::new(4, 3), 0.05)]);
+        assert!(map.colision_check(&Position::new(2, 3)));
+        assert!(!map.colision_check(&Position::new(4, 3)));
+        assert!(!map.colision_check(&Position::new(3, 2)));
+    }
+
+    #[test]
+    fn test_foods_passable_only_in_t3() {
+        // 果子压在蛇身上时，T3 的规则把它当成能走的格子，T1/T2 仍然按蛇身判断
+        let me = snake(&[2, 2, 2, 3, 3, 3, 3, 2]);
+        let food = Position::new(2, 3);
+        assert!(!Map::new(me.clone(), vec![], vec![food], 5).colision_check(&food));
+        assert!(Map::with_barriers(me, vec![food], vec![], 5).colision_check(&food));
+    }
+
+    #[test]
+    fn test_validate() {
+        let me = snake(&[1, 4, 1, 5, 2, 5, 3, 5]);
+        let other = snake(&[3, 1, 2, 1, 2, 2, 2, 3]);
+        assert_eq!(Map::new(me.clone(), vec![other.clone()], vec![Position::new(5, 5)], 5).validate(), vec![]);
+
+        // 对手的蛇身断开且走回头路，果子放在我方蛇身上、另一个在棋盘外
+        let other = snake(&[3, 1, 2, 1, 2, 2, 2, 1]);
+        let foods = vec![Position::new(2, 5), Position::new(6, 1)];
+        let violations = Map::new(me.clone(), vec![other, snake(&[4, 4, 4, 3, 5, 1])], foods, 5).validate();
+        assert_eq!(violations, vec![
+            Violation::NotAdjacent { snake: 2, first: Position::new(4, 3), second: Position::new(5, 1) },
+            Violation::Overlap { pos: Position::new(2, 1), first: Entity::Snake(1), second: Entity::Snake(1) },
+            Violation::Overlap { pos: Position::new(2, 5), first: Entity::Snake(0), second: Entity::Food },
+            Violation::OutOfBoard { entity: Entity::Food, pos: Position::new(6, 1) },
+        ]);
+
+        // T2：障碍物压在蛇身上
+        let map = Map::with_barriers(me, vec![], vec![Position::new(3, 5), Position::new(0, 3)], 5);
+        assert_eq!(map.validate(), vec![
+            Violation::Overlap { pos: Position::new(3, 5), first: Entity::Snake(0), second: Entity::Barrier },
+            Violation::OutOfBoard { entity: Entity::Barrier, pos: Position::new(0, 3) },
+        ]);
+    }
+}
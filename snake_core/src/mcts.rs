@@ -0,0 +1,321 @@
+//! T3 的蒙特卡洛树搜索（MCTS）策略
+//!
+//! 所有蛇同时行动，采用 decoupled UCT：树的每个节点为每条存活的蛇分别维护各个方向的
+//! 访问次数和累计收益，选择时每条蛇独立地按 UCB1 挑方向，组合成一个联合动作进入子节点。
+//! 叶子之后用轻量的随机走子（能吃就吃，否则随机选一个不会立刻撞死的方向）模拟若干回合，
+//! 局面推进复用 `search::SearchState`（基于 `BoardState`），与真实规则一致；新刷出的果子同样不做预测。
+//! `MctsTree` 可以跨回合保留：下一回合的局面恰好是树中某个子节点时，直接以它为根继续搜索。
+
+use crate::map::Direction;
+use crate::random::RandomGenerator;
+use crate::search::SearchState;
+use std::collections::HashMap;
+
+/// 搜索预算，迭代次数和时间任一用完就停止
+#[derive(Clone, Copy, Debug)]
+pub struct MctsBudget {
+    pub max_iterations: u32,
+    pub max_ms: f64,
+}
+
+/// MCTS 的参数
+#[derive(Clone, Copy, Debug)]
+pub struct MctsConfig {
+    pub budget: MctsBudget,
+    /// 每次模拟最多走的回合数
+    pub rollout_depth: i32,
+    /// UCB1 的探索系数
+    pub exploration: f64,
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            budget: MctsBudget { max_iterations: 2000, max_ms: 200.0 },
+            rollout_depth: 12,
+            exploration: 0.7,
+            seed: 2025,
+        }
+    }
+}
+
+/// 搜索结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct MctsResult {
+    /// 实际完成的迭代次数
+    pub iterations: u32,
+    /// 根节点上我方每个候选方向的访问次数和平均收益
+    pub stats: Vec<(Direction, u32, f64)>,
+}
+
+impl MctsResult {
+    /// 访问次数最多的方向，没有候选方向时返回 `None`
+    pub fn best_direction(&self) -> Option<Direction> {
+        self.stats.iter().max_by_key(|(_, visits, _)| *visits).map(|(direction, _, _)| *direction)
+    }
+}
+
+/// 某条蛇在某个节点上的一个方向
+struct Arm {
+    direction: Direction,
+    visits: u32,
+    reward: f64,
+}
+
+struct TreeNode {
+    state: SearchState,
+    /// 每条蛇的候选方向，死蛇只有一个占位方向
+    arms: Vec<Vec<Arm>>,
+    /// 以每条蛇所选方向的下标为键
+    children: HashMap<Vec<usize>, usize>,
+    visits: u32,
+}
+
+impl TreeNode {
+    fn new(state: SearchState) -> TreeNode {
+        let arms = (0..state.get_board().snake_count())
+            .map(|i| state.candidate_moves(i).into_iter().map(|direction| Arm { direction, visits: 0, reward: 0.0 }).collect())
+            .collect();
+        TreeNode { state, arms, children: HashMap::new(), visits: 0 }
+    }
+}
+
+/// 可以跨回合复用的搜索树
+pub struct MctsTree {
+    /// 第 0 个节点是根
+    nodes: Vec<TreeNode>,
+}
+
+impl MctsTree {
+    pub fn new(root: SearchState) -> MctsTree {
+        MctsTree { nodes: vec![TreeNode::new(root)] }
+    }
+
+    pub fn get_root(&self) -> &SearchState {
+        &self.nodes[0].state
+    }
+
+    /// 根节点累计的迭代次数，包括复用过来的
+    pub fn get_visits(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// 在当前树上继续搜索，`now` 返回当前时间（毫秒）
+    pub fn run(&mut self, config: &MctsConfig, now: &dyn Fn() -> f64) -> MctsResult {
+        let deadline = now() + config.budget.max_ms;
+        let mut rng = RandomGenerator::new(config.seed);
+        let tree = &mut self.nodes;
+
+        let mut iterations = 0;
+        while iterations < config.budget.max_iterations && (iterations == 0 || now() < deadline) {
+            // 选择与扩展
+            let mut path: Vec<(usize, Vec<usize>)> = Vec::new();
+            let mut current = 0;
+            while !tree[current].state.is_terminal() {
+                let joint = select_joint(&tree[current], config.exploration, &mut rng);
+                let child = tree[current].children.get(&joint).copied();
+                path.push((current, joint.clone()));
+                match child {
+                    Some(child) => current = child,
+                    None => {
+                        let actions: Vec<Direction> = joint.iter().enumerate()
+                            .map(|(i, arm)| tree[current].arms[i][*arm].direction)
+                            .collect();
+                        let state = tree[current].state.next(&actions);
+                        tree.push(TreeNode::new(state));
+                        let child = tree.len() - 1;
+                        tree[current].children.insert(joint, child);
+                        current = child;
+                        break;
+                    }
+                }
+            }
+
+            // 模拟与回传
+            let rewards = rollout(&tree[current].state, config.rollout_depth, &mut rng);
+            tree[current].visits += 1;
+            for (node, joint) in path {
+                tree[node].visits += 1;
+                for (i, arm) in joint.into_iter().enumerate() {
+                    let arm = &mut tree[node].arms[i][arm];
+                    arm.visits += 1;
+                    arm.reward += rewards[i];
+                }
+            }
+            iterations += 1;
+        }
+
+        let stats = if tree[0].state.is_terminal() {
+            vec![]
+        } else {
+            tree[0].arms[0].iter()
+                .map(|arm| (arm.direction, arm.visits, arm.reward / arm.visits.max(1) as f64))
+                .collect()
+        };
+        MctsResult { iterations, stats }
+    }
+
+    /// 走完一回合后，把根换成与 `observed` 局面相同的子节点，只保留它的子树
+    ///
+    /// 有果子被吃（会刷新出树里预料不到的果子）、有蛇死掉（输入中的编号会变）
+    /// 或者剩余回合数对不上时找不到这样的子节点，返回 `false`，树保持不变
+    pub fn advance(&mut self, observed: &SearchState) -> bool {
+        let Some(child) = self.nodes[0].children.values().copied().find(|child| {
+            let state = &self.nodes[*child].state;
+            state.get_rounds_left() == observed.get_rounds_left()
+                && state.get_eaten().iter().all(|eaten| *eaten == 0)
+                && state.get_board().same_position(observed.get_board())
+        }) else {
+            return false;
+        };
+
+        // 按先序把子树搬到新的数组里，重新编号
+        let mut old_nodes: Vec<Option<TreeNode>> = std::mem::take(&mut self.nodes).into_iter().map(Some).collect();
+        let mut stack = vec![(child, None)];
+        while let Some((old, parent)) = stack.pop() {
+            let mut node = old_nodes[old].take().expect("tree node visited twice");
+            let index = self.nodes.len();
+            if let Some((parent, joint)) = parent {
+                let parent: &mut TreeNode = &mut self.nodes[parent];
+                parent.children.insert(joint, index);
+            }
+            for (joint, grandchild) in std::mem::take(&mut node.children) {
+                stack.push((grandchild, Some((index, joint))));
+            }
+            self.nodes.push(node);
+        }
+        true
+    }
+}
+
+/// 从 `root` 开始做 MCTS，`now` 返回当前时间（毫秒）
+pub fn mcts(root: &SearchState, config: &MctsConfig, now: &dyn Fn() -> f64) -> MctsResult {
+    MctsTree::new(*root).run(config, now)
+}
+
+/// 每条蛇独立地按 UCB1 选择方向，没有访问过的方向优先
+fn select_joint(node: &TreeNode, exploration: f64, rng: &mut RandomGenerator) -> Vec<usize> {
+    let log_visits = (node.visits.max(1) as f64).ln();
+    node.arms.iter()
+        .map(|arms| {
+            let unvisited: Vec<usize> = (0..arms.len()).filter(|i| arms[*i].visits == 0).collect();
+            if !unvisited.is_empty() {
+                return unvisited[pick(rng, unvisited.len())];
+            }
+            let ucb = |arm: &Arm| arm.reward / arm.visits as f64 + exploration * (log_visits / arm.visits as f64).sqrt();
+            (0..arms.len()).max_by(|a, b| ucb(&arms[*a]).total_cmp(&ucb(&arms[*b]))).unwrap_or(0)
+        })
+        .collect()
+}
+
+/// 随机走子直到终局或走满 `depth` 回合，返回每条蛇的收益
+fn rollout(state: &SearchState, depth: i32, rng: &mut RandomGenerator) -> Vec<f64> {
+    let mut state = *state;
+    for _ in 0..depth {
+        if state.is_terminal() {
+            break;
+        }
+        let actions: Vec<Direction> = (0..state.get_board().snake_count())
+            .map(|i| rollout_move(&state, i, rng))
+            .collect();
+        state = state.next(&actions);
+    }
+    reward(&state)
+}
+
+/// 走子策略：旁边有果子就吃，否则在不会立刻撞死的方向中随机选
+fn rollout_move(state: &SearchState, i: usize, rng: &mut RandomGenerator) -> Direction {
+    let moves = state.candidate_moves(i);
+    if let Some(head) = state.get_board().head(i)
+        && let Some(direction) = moves.iter().find(|d| state.get_board().has_food(&head.step(**d))) {
+        return *direction;
+    }
+    moves[pick(rng, moves.len())]
+}
+
+/// 收益在 [0, 1] 之间：活着占一半，吃到的果子占另一半，吃得越多边际收益越小
+fn reward(state: &SearchState) -> Vec<f64> {
+    state.get_eaten().iter().enumerate()
+        .map(|(i, eaten)| {
+            let alive = if state.get_board().is_alive(i) { 0.5 } else { 0.0 };
+            alive + 0.5 * (1.0 - 0.5f64.powi(*eaten))
+        })
+        .collect()
+}
+
+/// 在 [0, len) 中随机取一个下标
+///
+/// `generate_int` 取的是线性同余结果的低位，对很小的范围周期太短，这里用高位
+fn pick(rng: &mut RandomGenerator, len: usize) -> usize {
+    (rng.generate_float(0.0, len as f64) as usize).min(len - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+    use crate::map::Position;
+
+    fn config(max_iterations: u32) -> MctsConfig {
+        MctsConfig { budget: MctsBudget { max_iterations, max_ms: f64::INFINITY }, ..MctsConfig::default() }
+    }
+
+    #[test]
+    fn test_iteration_budget() {
+        let root = SearchState::new(snake(&[1, 4, 1, 3, 1, 2, 1, 1]), vec![snake(&[5, 2, 5, 3, 5, 4, 5, 5])], vec![], 5, 50);
+        let result = mcts(&root, &config(300), &|| 0.0);
+        assert_eq!(result.iterations, 300);
+        assert_eq!(result.stats.iter().map(|(_, visits, _)| visits).sum::<u32>(), 300);
+        // 向下撞脖子、向左出界都不会出现在候选方向中
+        assert!(result.stats.iter().all(|(d, _, _)| *d == Direction::Up || *d == Direction::Right));
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let root = SearchState::new(snake(&[1, 4, 1, 3, 1, 2, 1, 1]), vec![], vec![], 5, 50);
+        let mut config = config(u32::MAX);
+        config.budget.max_ms = 0.0;
+        // 时间用完时至少也完成一次迭代
+        assert_eq!(mcts(&root, &config, &|| 0.0).iterations, 1);
+    }
+
+    #[test]
+    fn test_avoid_head_on_and_take_food() {
+        // 右边 (2,3) 对手也能走到，上面 (1,4) 有果子
+        let me = snake(&[1, 3, 1, 2, 1, 1, 2, 1]);
+        let other = snake(&[3, 3, 4, 3, 5, 3, 5, 4]);
+        let root = SearchState::new(me, vec![other], vec![Position::new(1, 4)], 5, 50);
+        let result = mcts(&root, &config(2000), &|| 0.0);
+        assert_eq!(result.best_direction(), Some(Direction::Up));
+    }
+
+    #[test]
+    fn test_reuse_subtree() {
+        let me = snake(&[3, 3, 3, 2, 3, 1, 2, 1]);
+        let other = snake(&[5, 5, 5, 4, 5, 3, 5, 2]);
+        let foods = vec![Position::new(1, 5)];
+        let mut tree = MctsTree::new(SearchState::new(me.clone(), vec![other.clone()], foods.clone(), 5, 50));
+        tree.run(&config(500), &|| 0.0);
+
+        // 我方向上、对手向左之后的局面
+        let observed = SearchState::new(me.moved(Direction::Up), vec![other.moved(Direction::Left)], foods.clone(), 5, 49);
+        assert!(tree.advance(&observed));
+        let reused = tree.get_visits();
+        assert!(reused > 0);
+        assert_eq!(tree.get_root().get_board().body(0), observed.get_board().body(0));
+        let result = tree.run(&config(100), &|| 0.0);
+        assert_eq!(result.iterations, 100);
+        assert_eq!(tree.get_visits(), reused + 100);
+
+        // 果子位置对不上，不能复用
+        let observed = SearchState::new(me.moved(Direction::Up).moved(Direction::Up), vec![other.moved(Direction::Left).moved(Direction::Left)], vec![Position::new(1, 1)], 5, 48);
+        assert!(!tree.advance(&observed));
+    }
+
+    #[test]
+    fn test_same_seed_same_result() {
+        let root = SearchState::new(snake(&[3, 3, 3, 2, 3, 1, 2, 1]), vec![snake(&[5, 5, 5, 4, 5, 3, 5, 2])], vec![Position::new(1, 1)], 5, 50);
+        assert_eq!(mcts(&root, &config(500), &|| 0.0), mcts(&root, &config(500), &|| 0.0));
+    }
+}
@@ -0,0 +1,349 @@
+//! 局面的文本表示，测试用例、问题报告和回放可以用同一种写法描述局面
+//!
+//! 每个格子一个字符，第一行是 y 最大的一行，每行从 x = 1 开始：
+//!
+//! * `A`..`Z`：蛇头，`A` 是我方（`Map::get_my_snake`），其余按 `Map::get_other_snakes` 的顺序；
+//! * `^` `v` `<` `>`：蛇身，箭头指向前一节（靠近蛇头的那一节），蛇尾也画成箭头；
+//! * `*` 果子，`#` 障碍物，`.` 空格子。
+//!
+//! ```text
+//! .....
+//! .A<<.
+//! ...^.
+//! #.*..
+//! .....
+//! ```
+//!
+//! 一行的紧凑写法把各行用 `/` 连起来，连续的空格子写成数字：`5/1A<<1/3^1/#1*2/5`。
+//! 解析时两种写法都接受，行内的空白会被忽略。每一节都指向唯一的前一节，所以从蛇头出发就能还原蛇身的顺序，
+//! 盘起来的蛇也没有歧义；只有同一条蛇的几节重叠在一个格子里时无法表示。
+//! 解析出来的果子和障碍物按从上到下、从左到右的顺序排列。
+
+use crate::game::GameState;
+use crate::map::{Direction, Map, Position, Snake};
+use std::fmt;
+
+pub const EMPTY: char = '.';
+pub const FOOD: char = '*';
+pub const BARRIER: char = '#';
+/// 紧凑写法中的行分隔符
+pub const ROW_SEPARATOR: char = '/';
+
+/// 解析出来的局面，`snakes[0]` 是我方
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Board {
+    pub size: i32,
+    pub snakes: Vec<Snake>,
+    pub foods: Vec<Position>,
+    pub barriers: Vec<Position>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NotationError {
+    /// 没有任何一行
+    EmptyBoard,
+    /// 棋盘必须是正方形，`row` 从 1 开始、从上往下数
+    NotSquare { row: usize, width: usize, size: usize },
+    UnknownSymbol { row: usize, symbol: char },
+    DuplicateHead(char),
+    /// 蛇头的字母不连续，比如有 `C` 没有 `B`
+    MissingHead(char),
+    /// 几节蛇身都指向 `pos`
+    BranchingBody { pos: Position },
+    /// 这节蛇身连不到任何蛇头
+    DetachedBody { pos: Position },
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::EmptyBoard => write!(f, "board has no rows"),
+            NotationError::NotSquare { row, width, size } =>
+                write!(f, "row {row} has {width} cells, expected {size}"),
+            NotationError::UnknownSymbol { row, symbol } => write!(f, "unknown symbol `{symbol}` in row {row}"),
+            NotationError::DuplicateHead(head) => write!(f, "more than one head `{head}`"),
+            NotationError::MissingHead(head) => write!(f, "head `{head}` is missing"),
+            NotationError::BranchingBody { pos } =>
+                write!(f, "more than one segment points at ({}, {})", pos.get_x(), pos.get_y()),
+            NotationError::DetachedBody { pos } =>
+                write!(f, "segment at ({}, {}) is not connected to a head", pos.get_x(), pos.get_y()),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// 蛇身箭头指向的方向
+fn arrow_direction(symbol: char) -> Option<Direction> {
+    match symbol {
+        '^' => Some(Direction::Up),
+        'v' => Some(Direction::Down),
+        '<' => Some(Direction::Left),
+        '>' => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+pub(crate) fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '^',
+        Direction::Down => 'v',
+        Direction::Left => '<',
+        Direction::Right => '>',
+    }
+}
+
+/// 把坐标展平成 `[x1, y1, x2, y2, ...]`，即 wasm 导出函数的参数格式
+pub fn coords(positions: &[Position]) -> Vec<i32> {
+    positions.iter().flat_map(|pos| [pos.get_x(), pos.get_y()]).collect()
+}
+
+impl Board {
+    /// 解析多行的网格或一行的紧凑写法
+    pub fn parse(text: &str) -> Result<Board, NotationError> {
+        let mut rows: Vec<Vec<char>> = Vec::new();
+        for line in text.split(['\n', ROW_SEPARATOR]) {
+            let mut cells = Vec::new();
+            let mut run = 0;
+            for c in line.chars().filter(|c| !c.is_whitespace()) {
+                if let Some(digit) = c.to_digit(10) {
+                    run = run * 10 + digit as usize;
+                    continue;
+                }
+                cells.extend(std::iter::repeat_n(EMPTY, run));
+                run = 0;
+                cells.push(c);
+            }
+            cells.extend(std::iter::repeat_n(EMPTY, run));
+            if !cells.is_empty() {
+                rows.push(cells);
+            }
+        }
+        if rows.is_empty() {
+            return Err(NotationError::EmptyBoard);
+        }
+
+        let size = rows.len();
+        let mut board = Board { size: size as i32, ..Board::default() };
+        let mut heads: Vec<Option<Position>> = Vec::new();
+        let mut arrows: Vec<(Position, Direction)> = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != size {
+                return Err(NotationError::NotSquare { row: i + 1, width: row.len(), size });
+            }
+            for (j, symbol) in row.iter().enumerate() {
+                let pos = Position::new(j as i32 + 1, (size - i) as i32);
+                match *symbol {
+                    EMPTY => {}
+                    FOOD => board.foods.push(pos),
+                    BARRIER => board.barriers.push(pos),
+                    'A'..='Z' => {
+                        let index = (*symbol as u8 - b'A') as usize;
+                        if heads.len() <= index {
+                            heads.resize(index + 1, None);
+                        }
+                        if heads[index].replace(pos).is_some() {
+                            return Err(NotationError::DuplicateHead(*symbol));
+                        }
+                    }
+                    symbol => match arrow_direction(symbol) {
+                        Some(direction) => arrows.push((pos, direction)),
+                        None => return Err(NotationError::UnknownSymbol { row: i + 1, symbol }),
+                    },
+                }
+            }
+        }
+
+        // 从蛇头出发，每次找指向当前这一节的箭头
+        let mut used = vec![false; arrows.len()];
+        for (index, head) in heads.iter().enumerate() {
+            let head = head.ok_or(NotationError::MissingHead((b'A' + index as u8) as char))?;
+            let mut body = vec![head];
+            loop {
+                let last = body[body.len() - 1];
+                let mut next = arrows.iter().enumerate().filter(|(_, (pos, direction))| pos.step(*direction) == last);
+                let Some((k, (pos, _))) = next.next() else {
+                    break;
+                };
+                if next.next().is_some() {
+                    return Err(NotationError::BranchingBody { pos: last });
+                }
+                used[k] = true;
+                body.push(*pos);
+            }
+            board.snakes.push(Snake::from_body(body));
+        }
+        if let Some(k) = used.iter().position(|used| !used) {
+            return Err(NotationError::DetachedBody { pos: arrows[k].0 });
+        }
+        Ok(board)
+    }
+
+    pub fn from_map(map: &Map) -> Board {
+        Board {
+            size: map.get_size(),
+            snakes: std::iter::once(map.get_my_snake()).chain(map.get_other_snakes()).cloned().collect(),
+            foods: map.get_foods().clone(),
+            barriers: map.get_barriers().clone(),
+        }
+    }
+
+    /// 对局中的局面，活着的蛇按座位顺序从 `A` 开始编号，死掉的蛇不占字母
+    pub fn from_game(state: &GameState) -> Board {
+        Board {
+            size: state.get_config().board_size,
+            snakes: state.get_snakes().iter().flatten().cloned().collect(),
+            foods: state.get_foods().clone(),
+            barriers: vec![],
+        }
+    }
+
+    /// 有其他蛇时按 T3 的 `Map::new` 构造，否则按 T1/T2 的 `Map::with_barriers`；两者不能同时出现
+    pub fn to_map(&self) -> Map {
+        let my_snake = self.snakes.first().cloned().unwrap_or_default();
+        if self.snakes.len() > 1 {
+            assert!(self.barriers.is_empty(), "a board with other snakes cannot have barriers");
+            Map::new(my_snake, self.snakes[1..].to_vec(), self.foods.clone(), self.size)
+        } else {
+            Map::with_barriers(my_snake, self.foods.clone(), self.barriers.clone(), self.size)
+        }
+    }
+
+    /// 第 `i` 条蛇展平后的坐标
+    pub fn snake_coords(&self, i: usize) -> Vec<i32> {
+        coords(self.snakes[i].get_body())
+    }
+
+    /// 除我方外所有蛇首尾相接展平后的坐标，即 `greedy_snake_step` 的 `input_other_snakes`
+    pub fn other_snake_coords(&self) -> Vec<i32> {
+        self.snakes.iter().skip(1).flat_map(|snake| coords(snake.get_body())).collect()
+    }
+
+    /// 按行排列的字符，第一行是 y 最大的一行
+    fn cells(&self) -> Vec<Vec<char>> {
+        let size = self.size.max(0) as usize;
+        let mut cells = vec![vec![EMPTY; size]; size];
+        let mut put = |pos: &Position, symbol: char| {
+            let (x, y) = (pos.get_x() as usize, pos.get_y() as usize);
+            if (1..=size).contains(&x) && (1..=size).contains(&y) {
+                cells[size - y][x - 1] = symbol;
+            }
+        };
+        for pos in &self.foods {
+            put(pos, FOOD);
+        }
+        for pos in &self.barriers {
+            put(pos, BARRIER);
+        }
+        for (i, snake) in self.snakes.iter().enumerate() {
+            let body = snake.get_body();
+            for pair in body.windows(2) {
+                if let Some(direction) = Direction::iter().find(|d| pair[1].step(*d) == pair[0]) {
+                    put(&pair[1], arrow(direction));
+                }
+            }
+            if let Some(head) = body.first() {
+                put(head, (b'A' + i as u8) as char);
+            }
+        }
+        cells
+    }
+
+    /// 一行的紧凑写法
+    pub fn to_compact(&self) -> String {
+        let rows: Vec<String> = self.cells().iter()
+            .map(|row| {
+                let mut out = String::new();
+                let mut run = 0;
+                for c in row {
+                    if *c == EMPTY {
+                        run += 1;
+                        continue;
+                    }
+                    if run > 0 {
+                        out.push_str(&run.to_string());
+                        run = 0;
+                    }
+                    out.push(*c);
+                }
+                if run > 0 {
+                    out.push_str(&run.to_string());
+                }
+                out
+            })
+            .collect();
+        rows.join(&ROW_SEPARATOR.to_string())
+    }
+}
+
+/// 多行的网格写法，每行以换行结尾
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells() {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameConfig;
+
+    #[test]
+    fn test_parse_grid() {
+        let board = Board::parse("
+            .....
+            .A<<.
+            ...^.
+            #.*..
+            .B<..
+        ").unwrap();
+        assert_eq!(board.size, 5);
+        assert_eq!(board.snake_coords(0), vec![2, 4, 3, 4, 4, 4, 4, 3]);
+        assert_eq!(board.other_snake_coords(), vec![2, 1, 3, 1]);
+        assert_eq!(board.foods, vec![Position::new(3, 2)]);
+        assert_eq!(board.barriers, vec![Position::new(1, 2)]);
+        assert_eq!(board.to_compact(), "5/1A<<1/3^1/#1*2/1B<2");
+        assert_eq!(Board::parse(&board.to_compact()).unwrap(), board);
+        assert_eq!(Board::parse(&board.to_string()).unwrap(), board);
+    }
+
+    #[test]
+    fn test_coiled_snake() {
+        // 盘成正方形的蛇，蛇头的两个邻居都是蛇身，只有箭头能分出先后
+        let body = vec![Position::new(1, 1), Position::new(1, 2), Position::new(2, 2), Position::new(2, 1)];
+        let map = Map::with_barriers(Snake::from_body(body.clone()), vec![Position::new(3, 3)], vec![], 3);
+        let board = Board::from_map(&map);
+        assert_eq!(board.to_string(), "..*\nv<.\nA^.\n");
+        let parsed = Board::parse(&board.to_string()).unwrap().to_map();
+        assert_eq!(parsed.get_my_snake().get_body(), &body[..]);
+        assert_eq!(parsed.get_foods(), map.get_foods());
+        assert!(parsed.validate().is_empty());
+    }
+
+    #[test]
+    fn test_from_game() {
+        let mut state = GameState::new(GameConfig::one_vs_one(), 1);
+        state.step(&[3, 1]);
+        let board = Board::from_game(&state);
+        assert_eq!(board.snake_coords(0), vec![2, 4, 1, 4, 1, 3, 1, 2]);
+        assert_eq!(board.snake_coords(1), vec![4, 2, 5, 2, 5, 3, 5, 4]);
+        // 果子按从上到下、从左到右的顺序解析出来，与对局中的顺序不同
+        let parsed = Board::parse(&board.to_compact()).unwrap();
+        assert_eq!(parsed.snakes, board.snakes);
+        assert_eq!(parsed.to_compact(), board.to_compact());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Board::parse(" \n "), Err(NotationError::EmptyBoard));
+        assert_eq!(Board::parse("A./..."), Err(NotationError::NotSquare { row: 2, width: 3, size: 2 }));
+        assert_eq!(Board::parse("A?/2"), Err(NotationError::UnknownSymbol { row: 1, symbol: '?' }));
+        assert_eq!(Board::parse("AA/2"), Err(NotationError::DuplicateHead('A')));
+        assert_eq!(Board::parse("AC/2"), Err(NotationError::MissingHead('B')));
+        assert_eq!(Board::parse("A</^1"), Err(NotationError::BranchingBody { pos: Position::new(1, 2) }));
+        assert_eq!(Board::parse("A1/1>"), Err(NotationError::DetachedBody { pos: Position::new(2, 1) }));
+    }
+}
@@ -0,0 +1,237 @@
+//! T3 的对手模型：跨回合跟踪其他蛇，估计它们的行为方式，预测下一步蛇头的位置
+//!
+//! 输入中只会给出还活着的蛇，死掉的蛇被去掉后后面的编号会前移，所以不能按编号对应。
+//! T3 中蛇长不变，一条蛇走一步后新身体去掉蛇头正好是旧身体去掉蛇尾，据此把前后两回合的蛇对应起来。
+//! 每条对手蛇对几种典型策略各有一个后验权重，每观察到它走一步，就按各策略在上一回合局面下
+//! 选出这一步的概率更新权重；预测时按权重把各策略给出的方向分布混合起来。
+
+use crate::map::{Direction, Position, Snake};
+
+/// 每种策略都以这个概率在四个方向中均匀乱走，免得某一步与策略不符就把权重清零
+const EPSILON: f64 = 0.1;
+
+/// 对手可能采用的策略
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Policy {
+    /// 朝最近（曼哈顿距离）的果子走
+    Greedy,
+    /// 在不会立刻撞死的方向中随机走
+    Random,
+    /// 朝我方蛇头走
+    Aggressive,
+}
+
+impl Policy {
+    pub fn iter() -> impl Iterator<Item = Policy> {
+        [Policy::Greedy, Policy::Random, Policy::Aggressive].into_iter()
+    }
+}
+
+/// 一回合的局面
+#[derive(Clone, Debug)]
+struct Snapshot {
+    size: i32,
+    my_snake: Snake,
+    other_snakes: Vec<Snake>,
+    foods: Vec<Position>,
+}
+
+impl Snapshot {
+    /// 第 `i` 条对手蛇走向 `direction` 是否会撞死；蛇尾会让出来，所以不算
+    fn is_deadly(&self, i: usize, direction: Direction) -> bool {
+        let pos = self.other_snakes[i].get_head().step(direction);
+        let in_board = pos.get_x() >= 1 && pos.get_x() <= self.size && pos.get_y() >= 1 && pos.get_y() <= self.size;
+        !in_board || std::iter::once(&self.my_snake).chain(&self.other_snakes).any(|snake| snake.get_without_tail().contains(&pos))
+    }
+
+    /// 第 `i` 条对手蛇按 `policy` 走每个方向的概率，顺序与 `Direction::iter` 相同
+    fn policy_distribution(&self, i: usize, policy: Policy) -> [f64; 4] {
+        let head = *self.other_snakes[i].get_head();
+        let safe: Vec<Direction> = Direction::iter().filter(|direction| !self.is_deadly(i, *direction)).collect();
+        let candidates = if safe.is_empty() { Direction::iter().collect() } else { safe };
+
+        // 按策略给候选方向打分，分数最低的方向平分概率
+        let score = |direction: &Direction| {
+            let pos = head.step(*direction);
+            match policy {
+                Policy::Greedy => self.foods.iter().map(|food| pos.manhattan(food)).min().unwrap_or(0),
+                Policy::Random => 0,
+                Policy::Aggressive => pos.manhattan(self.my_snake.get_head()),
+            }
+        };
+        let best = candidates.iter().map(score).min().unwrap_or(0);
+        let chosen: Vec<Direction> = candidates.into_iter().filter(|direction| score(direction) == best).collect();
+
+        let mut distribution = [EPSILON / 4.0; 4];
+        for (k, direction) in Direction::iter().enumerate() {
+            if chosen.contains(&direction) {
+                distribution[k] += (1.0 - EPSILON) / chosen.len() as f64;
+            }
+        }
+        distribution
+    }
+}
+
+/// 一条被跟踪的对手蛇
+#[derive(Clone, Debug)]
+struct Track {
+    id: u32,
+    /// 各策略的后验权重，顺序与 `Policy::iter` 相同，和为 1
+    weights: [f64; 3],
+}
+
+/// 跨回合的对手模型，每回合调用一次 `observe`
+#[derive(Clone, Debug, Default)]
+pub struct OpponentModel {
+    last: Option<Snapshot>,
+    /// 与上一次观察到的 `other_snakes` 一一对应
+    tracks: Vec<Track>,
+    next_id: u32,
+    observations: usize,
+}
+
+impl OpponentModel {
+    pub fn new() -> OpponentModel {
+        OpponentModel::default()
+    }
+
+    /// 只观察一回合的模型，各策略权重相同
+    pub fn from_snapshot(my_snake: &Snake, other_snakes: &[Snake], foods: &[Position], size: i32) -> OpponentModel {
+        let mut model = OpponentModel::new();
+        model.observe(my_snake, other_snakes, foods, size);
+        model
+    }
+
+    /// 记下这一回合的局面，把每条对手蛇与上一回合的蛇对应起来，并按它走的这一步更新策略权重
+    ///
+    /// 对应不上的蛇（第一次出现，或者中间漏掉了回合）从均匀的权重开始
+    pub fn observe(&mut self, my_snake: &Snake, other_snakes: &[Snake], foods: &[Position], size: i32) {
+        let mut tracks = Vec::with_capacity(other_snakes.len());
+        for snake in other_snakes {
+            let matched = self.last.as_ref().and_then(|last| {
+                last.other_snakes.iter().position(|old| moved_from(old, snake)).map(|j| (last, j))
+            });
+            let track = match matched {
+                Some((last, j)) => {
+                    let mut track = self.tracks[j].clone();
+                    let head = last.other_snakes[j].get_head();
+                    if let Some(k) = Direction::iter().position(|direction| head.step(direction) == *snake.get_head()) {
+                        for (weight, policy) in track.weights.iter_mut().zip(Policy::iter()) {
+                            *weight *= last.policy_distribution(j, policy)[k];
+                        }
+                        let total: f64 = track.weights.iter().sum();
+                        track.weights.iter_mut().for_each(|weight| *weight /= total);
+                    }
+                    track
+                }
+                None => {
+                    self.next_id += 1;
+                    Track { id: self.next_id, weights: [1.0 / 3.0; 3] }
+                }
+            };
+            tracks.push(track);
+        }
+        self.tracks = tracks;
+        self.last = Some(Snapshot { size, my_snake: my_snake.clone(), other_snakes: other_snakes.to_vec(), foods: foods.to_vec() });
+        self.observations += 1;
+    }
+
+    /// 已经观察过的回合数
+    pub fn get_observations(&self) -> usize {
+        self.observations
+    }
+
+    /// 最近一次观察中第 `i` 条对手蛇的编号，同一条蛇在各回合中编号不变
+    pub fn track_id(&self, i: usize) -> u32 {
+        self.tracks[i].id
+    }
+
+    /// 最近一次观察中第 `i` 条对手蛇各策略的后验权重
+    pub fn policy_weights(&self, i: usize) -> Vec<(Policy, f64)> {
+        Policy::iter().zip(self.tracks[i].weights).collect()
+    }
+
+    /// 最近一次观察中第 `i` 条对手蛇下一步蛇头落在各个格子的概率，和为 1
+    pub fn head_distribution(&self, i: usize) -> Vec<(Position, f64)> {
+        let Some(last) = &self.last else {
+            return vec![];
+        };
+        let head = *last.other_snakes[i].get_head();
+        let mut distribution = [0.0; 4];
+        for (policy, weight) in self.policy_weights(i) {
+            for (p, q) in distribution.iter_mut().zip(last.policy_distribution(i, policy)) {
+                *p += weight * q;
+            }
+        }
+        Direction::iter().zip(distribution).map(|(direction, p)| (head.step(direction), p)).collect()
+    }
+
+    /// 每个格子下一回合被至少一个对手蛇头占据的概率，只列出概率大于 0 的格子
+    pub fn head_risks(&self) -> Vec<(Position, f64)> {
+        let count = self.last.as_ref().map_or(0, |last| last.other_snakes.len());
+        let mut risks: Vec<(Position, f64)> = Vec::new();
+        for i in 0..count {
+            for (pos, p) in self.head_distribution(i) {
+                // 各条蛇相互独立：1 - (1 - a)(1 - b)
+                match risks.iter_mut().find(|(cell, _)| *cell == pos) {
+                    Some((_, risk)) => *risk = 1.0 - (1.0 - *risk) * (1.0 - p),
+                    None => risks.push((pos, p)),
+                }
+            }
+        }
+        risks
+    }
+}
+
+/// `new` 是否是 `old` 走了一步之后的样子
+fn moved_from(old: &Snake, new: &Snake) -> bool {
+    let (old, new) = (old.get_body(), new.get_body());
+    old.len() == new.len() && !new.is_empty() && new[1..] == old[..old.len() - 1] && old[0].manhattan(&new[0]) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    #[test]
+    fn test_learn_greedy_opponent() {
+        // 对手每回合都朝 (8,5) 的果子走，我方不动
+        let me = snake(&[1, 1, 1, 2, 1, 3, 1, 4]);
+        let foods = vec![Position::new(8, 5)];
+        let mut other = snake(&[3, 5, 3, 6, 3, 7, 3, 8]);
+        let mut model = OpponentModel::new();
+        for _ in 0..4 {
+            model.observe(&me, std::slice::from_ref(&other), &foods, 8);
+            other = other.moved(Direction::Right);
+        }
+        let weights = model.policy_weights(0);
+        assert_eq!(weights[0].0, Policy::Greedy);
+        assert!(weights[0].1 > 0.9, "{:?}", weights);
+
+        // 学到之后，朝果子的方向几乎是唯一的预测
+        let distribution = model.head_distribution(0);
+        let (best, p) = distribution.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        assert_eq!(best, Position::new(7, 5));
+        assert!(p > 0.8);
+        assert!((distribution.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_match_after_death() {
+        let me = snake(&[1, 1, 1, 2, 1, 3, 1, 4]);
+        let a = snake(&[3, 3, 3, 4, 3, 5, 3, 6]);
+        let b = snake(&[6, 3, 6, 4, 6, 5, 6, 6]);
+        let mut model = OpponentModel::new();
+        model.observe(&me, &[a.clone(), b.clone()], &[], 8);
+        let id_b = model.track_id(1);
+
+        // a 死掉了，b 的下标从 1 变成 0，仍然是同一条蛇
+        model.observe(&me, &[b.moved(Direction::Down)], &[], 8);
+        assert_eq!(model.track_id(0), id_b);
+        // 对不上的蛇当成新的蛇
+        model.observe(&me, &[a], &[], 8);
+        assert!(model.track_id(0) > id_b);
+        assert_eq!(model.get_observations(), 3);
+    }
+}
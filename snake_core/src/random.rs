@@ -46,7 +46,8 @@ impl RandomGenerator {
     /// # 返回值
     /// 返回指定范围内的随机浮点数
     pub fn generate_float(&mut self, min: f64, max: f64) -> f64 {
-        let random = self.next() as f64 / u64::MAX as f64;
+        // `next` 的结果在 [0, 2^63) 内，除以 u64::MAX 只能得到区间的前一半
+        let random = self.next() as f64 / (1u64 << 63) as f64;
         min + random * (max - min)
     }
 
@@ -55,6 +56,19 @@ impl RandomGenerator {
     /// # 返回值
     /// 返回随机的布尔值（true 或 false）
     pub fn generate_bool(&mut self) -> bool {
-        self.next() % 2 == 0
+        self.next().is_multiple_of(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_covers_range() {
+        let mut rng = RandomGenerator::new(7);
+        let values: Vec<f64> = (0..1000).map(|_| rng.generate_float(2.0, 4.0)).collect();
+        assert!(values.iter().all(|v| (2.0..4.0).contains(v)));
+        assert!(values.iter().any(|v| *v > 3.5));
     }
 }
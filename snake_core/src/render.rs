@@ -0,0 +1,180 @@
+//! 在终端里画出局面，测试失败或调试时可以直接看到棋盘
+//!
+//! 格子的符号与 `notation` 相同（`A`..`Z` 蛇头、箭头蛇身、`*` 果子、`#` 障碍物），格子之间隔一个空格，
+//! 左边标 y、下面标 x（只标个位数）。计划的路线画成 `o`，热力图按最大值缩放成 `1`..`9` 画在空格子上。
+//! 打开 `ansi` 时蛇按编号上色，热力图改成背景色，蛇身上的数值也能看到。
+
+use crate::map::{Direction, Map, Position};
+use crate::notation::{self, Board};
+use std::fmt;
+
+/// 路线经过的空格子
+pub const PATH: char = 'o';
+
+/// 各条蛇的前景色，我方为绿色，超过 6 条时循环使用
+const SNAKE_COLORS: [u8; 6] = [92, 93, 94, 95, 96, 97];
+const FOOD_COLOR: u8 = 91;
+const BARRIER_COLOR: u8 = 90;
+const PATH_COLOR: u8 = 36;
+/// 热力图从低到高的背景色（256 色中的红色系）
+const HEAT_COLORS: [u8; 5] = [52, 88, 124, 160, 196];
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// 我方从蛇头出发计划走的路线
+    pub path: Vec<Direction>,
+    /// 每个格子的数值，比如 `OpponentModel::head_risks` 给出的概率，不大于 0 的不画
+    pub heatmap: Vec<(Position, f64)>,
+    /// 用 ANSI 转义序列上色
+    pub ansi: bool,
+}
+
+/// 一个格子上画什么
+#[derive(Clone, Copy)]
+enum Cell {
+    Empty,
+    Food,
+    Barrier,
+    Path,
+    Head(usize),
+    Body(usize, Direction),
+}
+
+pub fn render(map: &Map, options: &RenderOptions) -> String {
+    let board = Board::from_map(map);
+    let size = board.size.max(0) as usize;
+    let index = |pos: &Position| {
+        let (x, y) = (pos.get_x() as usize, pos.get_y() as usize);
+        ((1..=size).contains(&x) && (1..=size).contains(&y)).then(|| (size - y) * size + x - 1)
+    };
+
+    let mut cells = vec![Cell::Empty; size * size];
+    let head = map.get_my_snake().get_body().first().copied();
+    let path_cells = options.path.iter().scan(head, |pos, direction| {
+        *pos = pos.map(|pos| pos.step(*direction));
+        *pos
+    });
+    let layers = [
+        (path_cells.collect::<Vec<_>>(), Cell::Path),
+        (board.foods.clone(), Cell::Food),
+        (board.barriers.clone(), Cell::Barrier),
+    ];
+    for (positions, cell) in layers {
+        for i in positions.iter().filter_map(index) {
+            cells[i] = cell;
+        }
+    }
+    for (k, snake) in board.snakes.iter().enumerate() {
+        let body = snake.get_body();
+        for pair in body.windows(2) {
+            if let (Some(i), Some(direction)) = (index(&pair[1]), Direction::iter().find(|d| pair[1].step(*d) == pair[0])) {
+                cells[i] = Cell::Body(k, direction);
+            }
+        }
+        if let Some(i) = body.first().and_then(index) {
+            cells[i] = Cell::Head(k);
+        }
+    }
+
+    let max_heat = options.heatmap.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+    let mut heat = vec![0.0; size * size];
+    for (pos, v) in &options.heatmap {
+        if let Some(i) = index(pos) {
+            heat[i] = v / max_heat;
+        }
+    }
+
+    let width = size.to_string().len();
+    let mut out = String::new();
+    for row in 0..size {
+        out.push_str(&format!("{:>width$}", size - row));
+        for column in 0..size {
+            let i = row * size + column;
+            let (symbol, color) = match cells[i] {
+                Cell::Empty if heat[i] > 0.0 && !options.ansi => (char::from_digit((heat[i] * 9.0).round().max(1.0) as u32, 10).unwrap(), None),
+                Cell::Empty => (notation::EMPTY, None),
+                Cell::Food => (notation::FOOD, Some(FOOD_COLOR)),
+                Cell::Barrier => (notation::BARRIER, Some(BARRIER_COLOR)),
+                Cell::Path => (PATH, Some(PATH_COLOR)),
+                Cell::Head(k) => ((b'A' + k as u8) as char, Some(SNAKE_COLORS[k % SNAKE_COLORS.len()])),
+                Cell::Body(k, direction) => (notation::arrow(direction), Some(SNAKE_COLORS[k % SNAKE_COLORS.len()])),
+            };
+            out.push(' ');
+            if !options.ansi {
+                out.push(symbol);
+                continue;
+            }
+            if let Some(color) = color {
+                out.push_str(&format!("\x1b[{color}m"));
+            }
+            if heat[i] > 0.0 {
+                let level = ((heat[i] * HEAT_COLORS.len() as f64).ceil() as usize).clamp(1, HEAT_COLORS.len());
+                out.push_str(&format!("\x1b[48;5;{}m", HEAT_COLORS[level - 1]));
+            }
+            out.push(symbol);
+            if color.is_some() || heat[i] > 0.0 {
+                out.push_str(RESET);
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str(&" ".repeat(width));
+    for x in 1..=size {
+        out.push_str(&format!(" {}", x % 10));
+    }
+    out.push('\n');
+    out
+}
+
+/// 不带路线和热力图、不上色的棋盘
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&render(self, &RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Map {
+        Board::parse("
+            .....
+            .A<<.
+            ...^.
+            #.*..
+            .....
+        ").unwrap().to_map()
+    }
+
+    #[test]
+    fn test_plain() {
+        let map = sample();
+        let options = RenderOptions {
+            path: vec![Direction::Down, Direction::Down, Direction::Right],
+            heatmap: vec![(Position::new(5, 5), 0.5), (Position::new(4, 5), 0.25), (Position::new(2, 3), 1.0)],
+            ansi: false,
+        };
+        assert_eq!(render(&map, &options), "\
+5 . . . 2 5
+4 . A < < .
+3 . o . ^ .
+2 # o * . .
+1 . . . . .
+  1 2 3 4 5
+");
+        assert_eq!(map.to_string().lines().nth(3), Some("2 # . * . ."));
+    }
+
+    #[test]
+    fn test_ansi() {
+        let map = sample();
+        let options = RenderOptions { heatmap: vec![(Position::new(2, 4), 1.0)], ansi: true, ..Default::default() };
+        let text = render(&map, &options);
+        // 我方蛇头是绿色的，背景是热力图最高一档
+        assert!(text.contains("\x1b[92m\x1b[48;5;196mA\x1b[0m"));
+        assert!(text.contains("\x1b[91m*\x1b[0m"));
+        assert_eq!(text.lines().count(), 6);
+    }
+}
@@ -0,0 +1,647 @@
+//! T3 对局的回放记录
+//!
+//! 记录开局参数（棋盘、种子、每个座位上的策略、初始的蛇和果子）以及每回合每条蛇的动作、决策耗时、
+//! 死亡的蛇和原因、新补充的果子。有两种格式：
+//!
+//! * JSON：`{"format": "snake-replay", "version": 1, ...}`，坐标和 `greedy_snake_step` 的参数一样展平成
+//!   `[x1, y1, x2, y2, ...]`，种子可能超出 JSON 数字的精度，写成十进制字符串；
+//! * 二进制：以 `SNKR` 开头，整数都用 LEB128 变长编码（有符号数先做 zigzag），种子是 8 字节小端，
+//!   决策耗时是 4 字节小端的 `f32`，死亡原因按 `DeathCause::to_code` 编号，每回合通常只占十几个字节。
+//!
+//! 读取时版本号不是 `REPLAY_VERSION` 的一律拒绝，开局参数不合规则（见 `Replay::validate`）的也拒绝。
+//!
+//! `Replay::resimulate` 用记录的种子和动作重新跑一遍 `GameState`，逐回合核对死亡和新补的果子。
+//! 回放里不单独记录每回合的蛇身、果子和得分，因为它们都能由已经核对过的内容推出来：
+//! 规则是确定的，蛇的位置由初始位置和动作决定，死亡的蛇核对过；果子由初始果子、补充的果子和
+//! 蛇头的位置决定；得分只在蛇头吃到果子时增加。所以死亡和补充的果子逐回合都对得上时，
+//! 每回合的整个局面（蛇、果子、得分）也对得上，需要时从 `resimulate` 返回的 `GameState` 里取。
+
+use crate::board::{DeathCause, SNAKE_LENGTH};
+use crate::game::{GameConfig, GameState};
+use crate::json::Json;
+use crate::map::{Position, Snake};
+use std::collections::HashSet;
+use std::fmt;
+
+/// 当前的回放格式版本，两种格式共用
+pub const REPLAY_VERSION: u32 = 1;
+/// JSON 格式中 `format` 字段的值
+pub const JSON_FORMAT: &str = "snake-replay";
+/// 二进制格式开头的 4 个字节
+pub const BINARY_MAGIC: [u8; 4] = *b"SNKR";
+/// 读取时接受的最大棋盘边长，更大的棋盘开局时要枚举的格子太多，只可能是损坏的数据
+pub const MAX_BOARD_SIZE: i32 = 256;
+
+/// 一条蛇在一个回合中的动作
+#[derive(Clone, Debug, PartialEq)]
+pub struct Move {
+    pub seat: usize,
+    /// 实际结算的方向编号，超时的蛇为 `GameState::fallback_action` 的结果
+    pub action: i32,
+    /// 策略做出这次决策用的时间（毫秒）
+    pub decision_ms: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Death {
+    pub seat: usize,
+    pub cause: DeathCause,
+}
+
+/// 一个回合的记录
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Turn {
+    /// 按座位编号排列，只有这一回合开始时还活着的蛇
+    pub moves: Vec<Move>,
+    pub deaths: Vec<Death>,
+    /// 回合结束时补充的果子
+    pub food_spawns: Vec<Position>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub board_size: i32,
+    pub seed: u64,
+    pub max_rounds: i32,
+    pub food_count: usize,
+    /// 每个座位上的策略名
+    pub strategies: Vec<String>,
+    pub initial_snakes: Vec<Vec<Position>>,
+    pub initial_foods: Vec<Position>,
+    pub turns: Vec<Turn>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// JSON 语法错误，`pos` 为字节位置
+    Syntax { pos: usize, message: &'static str },
+    /// 不是回放文件
+    BadFormat,
+    UnsupportedVersion(u32),
+    /// 缺少字段或字段的类型不对
+    InvalidField(&'static str),
+    /// 二进制数据提前结束
+    Truncated,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Syntax { pos, message } => write!(f, "invalid JSON at byte {pos}: {message}"),
+            ReplayError::BadFormat => write!(f, "not a snake replay"),
+            ReplayError::UnsupportedVersion(version) => write!(f, "unsupported replay version {version}, expected {REPLAY_VERSION}"),
+            ReplayError::InvalidField(field) => write!(f, "missing or invalid field `{field}`"),
+            ReplayError::Truncated => write!(f, "replay data ends unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// 重新模拟时第一处与记录不一致的地方，`round` 从 1 开始，与 `GameState::get_dead_round` 一致
+#[derive(Clone, Debug, PartialEq)]
+pub enum Divergence {
+    /// 用记录的种子开局，生成的初始果子不同
+    InitialFoods { expected: Vec<Position>, actual: Vec<Position> },
+    /// 记录中这一回合行动的座位与规则认为还活着的座位不同
+    Seats { round: i32, expected: Vec<usize>, actual: Vec<usize> },
+    Deaths { round: i32, expected: Vec<Death>, actual: Vec<Death> },
+    FoodSpawns { round: i32, expected: Vec<Position>, actual: Vec<Position> },
+    /// 对局已经结束，记录里还有回合
+    ExtraTurns { round: i32 },
+}
+
+impl Divergence {
+    /// 出现分歧的回合，开局时为 0
+    pub fn round(&self) -> i32 {
+        match self {
+            Divergence::InitialFoods { .. } => 0,
+            Divergence::Seats { round, .. }
+            | Divergence::Deaths { round, .. }
+            | Divergence::FoodSpawns { round, .. }
+            | Divergence::ExtraTurns { round } => *round,
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells = |positions: &[Position]| positions.iter().map(|pos| format!("({}, {})", pos.get_x(), pos.get_y())).collect::<Vec<_>>().join(" ");
+        let deaths = |deaths: &[Death]| deaths.iter().map(|death| format!("{}:{}", death.seat, death.cause.name())).collect::<Vec<_>>().join(" ");
+        match self {
+            Divergence::InitialFoods { expected, actual } =>
+                write!(f, "initial foods: recorded [{}], simulated [{}]", cells(expected), cells(actual)),
+            Divergence::Seats { round, expected, actual } =>
+                write!(f, "round {round}: recorded moves for seats {expected:?}, alive seats are {actual:?}"),
+            Divergence::Deaths { round, expected, actual } =>
+                write!(f, "round {round}: recorded deaths [{}], simulated [{}]", deaths(expected), deaths(actual)),
+            Divergence::FoodSpawns { round, expected, actual } =>
+                write!(f, "round {round}: recorded food spawns [{}], simulated [{}]", cells(expected), cells(actual)),
+            Divergence::ExtraTurns { round } =>
+                write!(f, "round {round}: game is already over but the replay has more turns"),
+        }
+    }
+}
+
+impl Replay {
+    /// 从开局的 `GameState` 开始记录，`strategies` 按座位给出
+    pub fn start(state: &GameState, strategies: Vec<String>) -> Replay {
+        let config = state.get_config();
+        Replay {
+            board_size: config.board_size,
+            seed: state.get_seed(),
+            max_rounds: config.max_rounds,
+            food_count: config.food_count,
+            strategies,
+            initial_snakes: state.get_snakes().iter().flatten().map(|snake| snake.get_body().to_vec()).collect(),
+            initial_foods: state.get_foods().clone(),
+            turns: vec![],
+        }
+    }
+
+    /// 记下刚刚用 `GameState::step` 结算完的一个回合，`moves` 是交给 `step` 的动作
+    pub fn record(&mut self, moves: Vec<Move>, state: &GameState) {
+        let deaths = state.get_last_deaths().iter().map(|(seat, cause)| Death { seat: *seat, cause: *cause }).collect();
+        self.turns.push(Turn { moves, deaths, food_spawns: state.get_last_spawns().clone() });
+    }
+
+    /// 开局参数，交给 `GameState::new` 配合 `seed` 可以重新开始这一局
+    pub fn config(&self) -> GameConfig {
+        GameConfig {
+            board_size: self.board_size,
+            snake_count: self.initial_snakes.len(),
+            food_count: self.food_count,
+            max_rounds: self.max_rounds,
+            initial_snakes: self.initial_snakes.iter()
+                .map(|body| {
+                    let mut coords = [0; 8];
+                    for (slot, v) in coords.iter_mut().zip(flatten(body)) {
+                        *slot = v;
+                    }
+                    coords
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let number = |v: i64| Json::Number(v as f64);
+        let positions = |positions: &[Position]| Json::Array(flatten(positions).into_iter().map(|v| number(v as i64)).collect());
+        let turns = self.turns.iter()
+            .map(|turn| Json::Object(vec![
+                ("moves".to_string(), Json::Array(turn.moves.iter()
+                    .map(|m| Json::Object(vec![
+                        ("seat".to_string(), number(m.seat as i64)),
+                        ("action".to_string(), number(m.action as i64)),
+                        ("ms".to_string(), Json::Number(m.decision_ms)),
+                    ]))
+                    .collect())),
+                ("deaths".to_string(), Json::Array(turn.deaths.iter()
+                    .map(|death| Json::Object(vec![
+                        ("seat".to_string(), number(death.seat as i64)),
+                        ("cause".to_string(), Json::String(death.cause.name().to_string())),
+                    ]))
+                    .collect())),
+                ("food_spawns".to_string(), positions(&turn.food_spawns)),
+            ]))
+            .collect();
+        let root = Json::Object(vec![
+            ("format".to_string(), Json::String(JSON_FORMAT.to_string())),
+            ("version".to_string(), number(REPLAY_VERSION as i64)),
+            ("board_size".to_string(), number(self.board_size as i64)),
+            ("seed".to_string(), Json::String(self.seed.to_string())),
+            ("max_rounds".to_string(), number(self.max_rounds as i64)),
+            ("food_count".to_string(), number(self.food_count as i64)),
+            ("strategies".to_string(), Json::Array(self.strategies.iter().map(|name| Json::String(name.clone())).collect())),
+            ("initial_snakes".to_string(), Json::Array(self.initial_snakes.iter().map(|body| positions(body)).collect())),
+            ("initial_foods".to_string(), positions(&self.initial_foods)),
+            ("turns".to_string(), Json::Array(turns)),
+        ]);
+        let mut out = String::new();
+        root.write(&mut out);
+        out
+    }
+
+    pub fn from_json(text: &str) -> Result<Replay, ReplayError> {
+        let root = Json::parse(text).map_err(|(pos, message)| ReplayError::Syntax { pos, message })?;
+        if root.get("format").and_then(Json::as_str) != Some(JSON_FORMAT) {
+            return Err(ReplayError::BadFormat);
+        }
+        let version = root.get("version").and_then(Json::as_i64).ok_or(ReplayError::InvalidField("version"))?;
+        if version != REPLAY_VERSION as i64 {
+            return Err(ReplayError::UnsupportedVersion(version as u32));
+        }
+
+        let turns = array(&root, "turns")?.iter()
+            .map(|turn| {
+                let moves = array(turn, "moves")?.iter()
+                    .map(|m| Ok(Move {
+                        seat: integer(m, "seat")?,
+                        action: integer(m, "action")?,
+                        decision_ms: m.get("ms").and_then(Json::as_f64).ok_or(ReplayError::InvalidField("ms"))?,
+                    }))
+                    .collect::<Result<Vec<Move>, ReplayError>>()?;
+                let deaths = array(turn, "deaths")?.iter()
+                    .map(|death| Ok(Death {
+                        seat: integer(death, "seat")?,
+                        cause: death.get("cause").and_then(Json::as_str).and_then(DeathCause::from_name)
+                            .ok_or(ReplayError::InvalidField("cause"))?,
+                    }))
+                    .collect::<Result<Vec<Death>, ReplayError>>()?;
+                Ok(Turn { moves, deaths, food_spawns: positions(turn.get("food_spawns"), "food_spawns")? })
+            })
+            .collect::<Result<Vec<Turn>, ReplayError>>()?;
+
+        Replay {
+            board_size: integer(&root, "board_size")?,
+            seed: root.get("seed").and_then(Json::as_str).and_then(|seed| seed.parse().ok()).ok_or(ReplayError::InvalidField("seed"))?,
+            max_rounds: integer(&root, "max_rounds")?,
+            food_count: integer(&root, "food_count")?,
+            strategies: array(&root, "strategies")?.iter()
+                .map(|name| name.as_str().map(str::to_string).ok_or(ReplayError::InvalidField("strategies")))
+                .collect::<Result<Vec<String>, ReplayError>>()?,
+            initial_snakes: array(&root, "initial_snakes")?.iter()
+                .map(|body| positions(Some(body), "initial_snakes"))
+                .collect::<Result<Vec<Vec<Position>>, ReplayError>>()?,
+            initial_foods: positions(root.get("initial_foods"), "initial_foods")?,
+            turns,
+        }.validated()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = BinaryWriter { bytes: BINARY_MAGIC.to_vec() };
+        out.unsigned(REPLAY_VERSION as u64);
+        out.signed(self.board_size as i64);
+        out.bytes.extend(self.seed.to_le_bytes());
+        out.signed(self.max_rounds as i64);
+        out.unsigned(self.food_count as u64);
+        out.unsigned(self.strategies.len() as u64);
+        for name in &self.strategies {
+            out.unsigned(name.len() as u64);
+            out.bytes.extend(name.as_bytes());
+        }
+        out.unsigned(self.initial_snakes.len() as u64);
+        for body in &self.initial_snakes {
+            out.positions(body);
+        }
+        out.positions(&self.initial_foods);
+        out.unsigned(self.turns.len() as u64);
+        for turn in &self.turns {
+            out.unsigned(turn.moves.len() as u64);
+            for m in &turn.moves {
+                out.unsigned(m.seat as u64);
+                out.signed(m.action as i64);
+                out.bytes.extend((m.decision_ms as f32).to_le_bytes());
+            }
+            out.unsigned(turn.deaths.len() as u64);
+            for death in &turn.deaths {
+                out.unsigned(death.seat as u64);
+                out.unsigned(death.cause.to_code() as u64);
+            }
+            out.positions(&turn.food_spawns);
+        }
+        out.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, ReplayError> {
+        if bytes.get(..4) != Some(&BINARY_MAGIC[..]) {
+            return Err(ReplayError::BadFormat);
+        }
+        let mut input = BinaryReader { bytes, pos: 4 };
+        let version = input.unsigned()?;
+        if version != REPLAY_VERSION as u64 {
+            return Err(ReplayError::UnsupportedVersion(version as u32));
+        }
+        let board_size = input.signed()? as i32;
+        let seed = u64::from_le_bytes(input.take(8)?.try_into().unwrap());
+        let max_rounds = input.signed()? as i32;
+        let food_count = input.unsigned()? as usize;
+        let strategies = (0..input.count()?)
+            .map(|_| {
+                let len = input.unsigned()? as usize;
+                String::from_utf8(input.take(len)?.to_vec()).map_err(|_| ReplayError::InvalidField("strategies"))
+            })
+            .collect::<Result<Vec<String>, ReplayError>>()?;
+        let initial_snakes = (0..input.count()?).map(|_| input.positions()).collect::<Result<Vec<_>, _>>()?;
+        let initial_foods = input.positions()?;
+        let turns = (0..input.count()?)
+            .map(|_| {
+                let moves = (0..input.count()?)
+                    .map(|_| Ok(Move {
+                        seat: input.unsigned()? as usize,
+                        action: input.signed()? as i32,
+                        decision_ms: f32::from_le_bytes(input.take(4)?.try_into().unwrap()) as f64,
+                    }))
+                    .collect::<Result<Vec<Move>, ReplayError>>()?;
+                let deaths = (0..input.count()?)
+                    .map(|_| {
+                        let seat = input.unsigned()? as usize;
+                        let cause = i32::try_from(input.unsigned()?).ok().and_then(DeathCause::from_code).ok_or(ReplayError::InvalidField("cause"))?;
+                        Ok(Death { seat, cause })
+                    })
+                    .collect::<Result<Vec<Death>, ReplayError>>()?;
+                Ok(Turn { moves, deaths, food_spawns: input.positions()? })
+            })
+            .collect::<Result<Vec<Turn>, ReplayError>>()?;
+        if input.pos != bytes.len() {
+            return Err(ReplayError::BadFormat);
+        }
+        Replay { board_size, seed, max_rounds, food_count, strategies, initial_snakes, initial_foods, turns }.validated()
+    }
+
+    /// 检查开局参数能不能交给 `GameState::new`，`from_json` 和 `from_bytes` 读到的回放都检查过
+    ///
+    /// 棋盘边长在 `1..=MAX_BOARD_SIZE` 之内，每个座位都有策略名，每条蛇正好 `SNAKE_LENGTH` 节、
+    /// 首尾相连、都在棋盘内且互不重叠，棋盘格数不少于蛇身格数的两倍和果子数的两倍（与 `board` 的要求一致）。
+    /// 每回合的动作不需要检查：座位在 `resimulate` 中先和存活的座位核对，无效的方向按规则判死。
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        if !(1..=MAX_BOARD_SIZE).contains(&self.board_size) {
+            return Err(ReplayError::InvalidField("board_size"));
+        }
+        if self.max_rounds < 0 {
+            return Err(ReplayError::InvalidField("max_rounds"));
+        }
+        if self.strategies.len() != self.initial_snakes.len() {
+            return Err(ReplayError::InvalidField("strategies"));
+        }
+        let cells = (self.board_size * self.board_size) as usize;
+        let in_board = |pos: &Position| (1..=self.board_size).contains(&pos.get_x()) && (1..=self.board_size).contains(&pos.get_y());
+        let mut occupied = HashSet::new();
+        let snakes_valid = cells >= 2 * SNAKE_LENGTH * self.initial_snakes.len()
+            && self.initial_snakes.iter().all(|body| {
+                body.len() == SNAKE_LENGTH
+                    && Snake::from_body(body.clone()).is_continuous()
+                    && body.iter().all(|pos| in_board(pos) && occupied.insert(*pos))
+            });
+        if !snakes_valid {
+            return Err(ReplayError::InvalidField("initial_snakes"));
+        }
+        if self.food_count > cells / 2 {
+            return Err(ReplayError::InvalidField("food_count"));
+        }
+        Ok(())
+    }
+
+    fn validated(self) -> Result<Replay, ReplayError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+impl Replay {
+    /// 按规则重新模拟整局，返回最后的局面，遇到第一处分歧就停下
+    ///
+    /// 每回合结算之前调用一次 `visit`，此时的 `GameState` 就是各条蛇在这一回合看到的局面。
+    /// 逐回合只核对死亡和补充的果子，蛇身、果子和得分由这两项决定（见模块文档）。
+    ///
+    /// 开局参数必须通过 `validate`，否则 `GameState::new` 可能 panic；`from_json`、`from_bytes` 和
+    /// `start` 得到的回放都满足这一点，手动构造的回放应先调用 `validate`。
+    pub fn resimulate(&self, mut visit: impl FnMut(&GameState, &Turn)) -> Result<GameState, Divergence> {
+        let mut state = GameState::new(self.config(), self.seed);
+        if state.get_foods() != &self.initial_foods {
+            return Err(Divergence::InitialFoods { expected: self.initial_foods.clone(), actual: state.get_foods().clone() });
+        }
+        for turn in &self.turns {
+            let round = state.get_round() + 1;
+            if state.is_over() {
+                return Err(Divergence::ExtraTurns { round });
+            }
+            let seats: Vec<usize> = turn.moves.iter().map(|m| m.seat).collect();
+            if seats != state.alive_seats() {
+                return Err(Divergence::Seats { round, expected: seats, actual: state.alive_seats() });
+            }
+            visit(&state, turn);
+
+            let actions: Vec<i32> = turn.moves.iter().map(|m| m.action).collect();
+            state.step(&actions);
+            let deaths: Vec<Death> = state.get_last_deaths().iter().map(|(seat, cause)| Death { seat: *seat, cause: *cause }).collect();
+            if deaths != turn.deaths {
+                return Err(Divergence::Deaths { round, expected: turn.deaths.clone(), actual: deaths });
+            }
+            if state.get_last_spawns() != &turn.food_spawns {
+                return Err(Divergence::FoodSpawns { round, expected: turn.food_spawns.clone(), actual: state.get_last_spawns().clone() });
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn flatten(positions: &[Position]) -> Vec<i32> {
+    positions.iter().flat_map(|pos| [pos.get_x(), pos.get_y()]).collect()
+}
+
+fn integer<T: TryFrom<i64>>(value: &Json, field: &'static str) -> Result<T, ReplayError> {
+    value.get(field).and_then(Json::as_i64).and_then(|v| T::try_from(v).ok()).ok_or(ReplayError::InvalidField(field))
+}
+
+fn array<'a>(value: &'a Json, field: &'static str) -> Result<&'a [Json], ReplayError> {
+    value.get(field).and_then(Json::as_array).ok_or(ReplayError::InvalidField(field))
+}
+
+/// 展平的坐标数组
+fn positions(value: Option<&Json>, field: &'static str) -> Result<Vec<Position>, ReplayError> {
+    let coords = value.and_then(Json::as_array)
+        .and_then(|items| items.iter().map(|v| v.as_i64().and_then(|v| i32::try_from(v).ok())).collect::<Option<Vec<i32>>>())
+        .filter(|coords| coords.len() % 2 == 0)
+        .ok_or(ReplayError::InvalidField(field))?;
+    Ok(coords.chunks(2).map(|pair| Position::new(pair[0], pair[1])).collect())
+}
+
+struct BinaryWriter {
+    bytes: Vec<u8>,
+}
+
+impl BinaryWriter {
+    fn unsigned(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.bytes.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.bytes.push(v as u8);
+    }
+
+    fn signed(&mut self, v: i64) {
+        self.unsigned(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn positions(&mut self, positions: &[Position]) {
+        self.unsigned(positions.len() as u64);
+        for v in flatten(positions) {
+            self.signed(v as i64);
+        }
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BinaryReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], ReplayError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(ReplayError::Truncated)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn unsigned(&mut self) -> Result<u64, ReplayError> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            v |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(ReplayError::BadFormat)
+    }
+
+    fn signed(&mut self) -> Result<i64, ReplayError> {
+        let v = self.unsigned()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    /// 后面跟着的元素个数；每个元素至少占一个字节，个数不会超过剩下的字节数
+    fn count(&mut self) -> Result<usize, ReplayError> {
+        let count = self.unsigned()? as usize;
+        if count > self.bytes.len() - self.pos {
+            return Err(ReplayError::Truncated);
+        }
+        Ok(count)
+    }
+
+    fn positions(&mut self) -> Result<Vec<Position>, ReplayError> {
+        (0..self.count()?)
+            .map(|_| Ok(Position::new(self.signed()? as i32, self.signed()? as i32)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1v1 开局，两条蛇各走几步，第 3 回合一起撞上自己
+    fn recorded_game() -> Replay {
+        let mut state = GameState::new(GameConfig::one_vs_one(), 42);
+        let mut replay = Replay::start(&state, vec!["a".to_string(), "b \"quoted\"".to_string()]);
+        for actions in [[0, 2], [3, 1], [1, 3]] {
+            let seats = state.alive_seats();
+            let moves = seats.iter().zip(actions).map(|(seat, action)| Move { seat: *seat, action, decision_ms: 1.5 }).collect();
+            state.step(&actions);
+            replay.record(moves, &state);
+        }
+        replay
+    }
+
+    #[test]
+    fn test_record() {
+        let replay = recorded_game();
+        assert_eq!(replay.turns.len(), 3);
+        assert_eq!(replay.initial_snakes[1], vec![Position::new(5, 2), Position::new(5, 3), Position::new(5, 4), Position::new(5, 5)]);
+        assert_eq!(replay.initial_foods.len(), 5);
+        let deaths: Vec<Death> = replay.turns.iter().flat_map(|turn| turn.deaths.clone()).collect();
+        assert_eq!(deaths, vec![Death { seat: 0, cause: DeathCause::Body }, Death { seat: 1, cause: DeathCause::Body }]);
+        // 每吃掉一个果子补一个，一回合最多两个
+        assert!(replay.turns.iter().all(|turn| turn.food_spawns.len() <= 2));
+        assert_eq!(GameState::new(replay.config(), replay.seed).get_foods(), &replay.initial_foods);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let replay = recorded_game();
+        let text = replay.to_json();
+        assert!(text.starts_with(r#"{"format":"snake-replay","version":1,"#));
+        assert_eq!(Replay::from_json(&text).unwrap(), replay);
+
+        let newer = text.replacen(r#""version":1"#, r#""version":2"#, 1);
+        assert_eq!(Replay::from_json(&newer), Err(ReplayError::UnsupportedVersion(2)));
+        let broken = text.replacen(r#""cause":"body""#, r#""cause":"bored""#, 1);
+        assert_eq!(Replay::from_json(&broken), Err(ReplayError::InvalidField("cause")));
+        assert_eq!(Replay::from_json("[]"), Err(ReplayError::BadFormat));
+        assert!(matches!(Replay::from_json("{"), Err(ReplayError::Syntax { .. })));
+        assert_eq!(Replay::from_json(&"[".repeat(1_000_000)), Err(ReplayError::Syntax { pos: 64, message: "too deeply nested" }));
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let replay = recorded_game();
+        let bytes = replay.to_bytes();
+        assert!(bytes.len() * 4 < replay.to_json().len());
+        // 1.5 能用 f32 精确表示
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+
+        for len in 0..bytes.len() {
+            assert!(Replay::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(Replay::from_bytes(&newer), Err(ReplayError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn test_validate() {
+        let replay = recorded_game();
+        assert_eq!(replay.validate(), Ok(()));
+
+        // 2x2 的棋盘放不下两条蛇，以前会在重新模拟时 panic
+        let mut small = replay.clone();
+        small.board_size = 2;
+        assert_eq!(Replay::from_json(&small.to_json()), Err(ReplayError::InvalidField("initial_snakes")));
+        assert_eq!(Replay::from_bytes(&small.to_bytes()), Err(ReplayError::InvalidField("initial_snakes")));
+
+        let mut changed = replay.clone();
+        changed.board_size = 0;
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("board_size")));
+        changed.board_size = MAX_BOARD_SIZE + 1;
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("board_size")));
+
+        let mut changed = replay.clone();
+        changed.initial_snakes[0].pop();
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("initial_snakes")));
+        let mut changed = replay.clone();
+        changed.initial_snakes[0].swap(1, 2);
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("initial_snakes")));
+        let mut changed = replay.clone();
+        changed.initial_snakes[1] = changed.initial_snakes[0].clone();
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("initial_snakes")));
+        let mut changed = replay.clone();
+        changed.initial_snakes[1][3] = Position::new(6, 5);
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("initial_snakes")));
+
+        let mut changed = replay.clone();
+        changed.food_count = 13;
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("food_count")));
+        changed.food_count = usize::MAX;
+        assert_eq!(Replay::from_bytes(&changed.to_bytes()), Err(ReplayError::InvalidField("food_count")));
+
+        let mut changed = replay.clone();
+        changed.strategies.pop();
+        assert_eq!(changed.validate(), Err(ReplayError::InvalidField("strategies")));
+    }
+
+    #[test]
+    fn test_resimulate() {
+        let replay = recorded_game();
+        let mut visited = vec![];
+        let state = replay.resimulate(|state, turn| visited.push((state.get_round(), turn.moves.len()))).unwrap();
+        assert_eq!(visited, vec![(0, 2), (1, 2), (2, 2)]);
+        assert_eq!(state.get_dead_round(), &vec![3, 3]);
+
+        // 改掉第 3 回合我方的动作，蛇还活着，但记录的死亡对不上了
+        let mut changed = replay.clone();
+        changed.turns[2].moves[0].action = 2;
+        let divergence = changed.resimulate(|_, _| {}).unwrap_err();
+        assert_eq!(divergence, Divergence::Deaths { round: 3, expected: vec![Death { seat: 0, cause: DeathCause::Body }, Death { seat: 1, cause: DeathCause::Body }], actual: vec![Death { seat: 1, cause: DeathCause::Body }] });
+        assert_eq!(divergence.to_string(), "round 3: recorded deaths [0:body 1:body], simulated [1:body]");
+
+        let mut changed = replay.clone();
+        changed.seed += 1;
+        assert!(matches!(changed.resimulate(|_, _| {}), Err(Divergence::InitialFoods { .. })));
+
+        let mut changed = replay.clone();
+        changed.turns.push(Turn::default());
+        assert_eq!(changed.resimulate(|_, _| {}).unwrap_err().round(), 4);
+    }
+}
@@ -0,0 +1,152 @@
+//! T3 的头对头风险评估
+//!
+//! 两个蛇头走进同一个格子时同归于尽，而且碰撞优先于吃果子，所以抢一个对手也够得着的果子可能什么都得不到。
+//! 所有蛇每回合都走一步，两个蛇头所在格子的奇偶性（x + y 的奇偶）每回合同时翻转，
+//! 只有奇偶性相同、也就是曼哈顿距离为偶数的两个蛇头才可能同时走进同一个格子；
+//! 这一回合就能撞上的只有距离为 2 的蛇头。对每个方向列出这样的对手，用 `OpponentModel` 估计它们真的走过来的概率，
+//! 再与这一步吃到的果子和剩余回合里还能吃到的果子比较。死掉之后分数保留，只是失去以后的回合。
+
+use crate::map::{Direction, Map, Position};
+use crate::opponent::OpponentModel;
+
+/// 每个剩余回合大约能吃到的果子数，用来估计活下去的价值
+pub const FUTURE_FOOD_RATE: f64 = 0.5;
+/// 对手走到这一格的概率达到这个值时算作很可能撞上
+pub const LIKELY_THRESHOLD: f64 = 0.5;
+
+/// 风险等级
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum RiskLevel {
+    /// 这一回合没有对手蛇头能走到这一格
+    Safe,
+    /// 有对手能走到，但估计它走过来的概率低于 `LIKELY_THRESHOLD`
+    Possible,
+    /// 对手很可能走过来
+    Likely,
+    /// 出界、撞上障碍物或不会让出的蛇身
+    Deadly,
+}
+
+impl RiskLevel {
+    /// 导出给 JS 的编号，按风险从低到高为 0..=3
+    pub fn to_code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// 我方某个方向的风险
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveRisk {
+    pub direction: Direction,
+    /// 这一回合能走到同一格的对手，下标对应 `Map::get_other_snakes`
+    pub threats: Vec<usize>,
+    /// 至少一个对手走到这一格的概率
+    pub probability: f64,
+    pub level: RiskLevel,
+    /// 这一步吃到的果子
+    pub food: bool,
+    /// 期望收益：活下来的概率乘以这一步的果子加上剩余回合的价值
+    pub value: f64,
+}
+
+/// 两个蛇头是否可能在之后的某一回合同时走进同一个格子
+pub fn can_meet(a: &Position, b: &Position) -> bool {
+    a.manhattan(b) % 2 == 0
+}
+
+/// 评估我方四个方向的头对头风险
+///
+/// `opponents` 最近一次观察的必须是 `map` 中的这些蛇；`rounds_left` 包含当前回合
+pub fn assess_moves(map: &Map, opponents: &OpponentModel, rounds_left: i32) -> Vec<MoveRisk> {
+    let head = *map.get_my_snake().get_head();
+    let future = FUTURE_FOOD_RATE * (rounds_left - 1).max(0) as f64;
+    Direction::iter()
+        .map(|direction| {
+            let cell = head.step(direction);
+            let food = map.get_foods().contains(&cell);
+            if map.strict_obstacles_check(&cell) {
+                return MoveRisk { direction, threats: vec![], probability: 1.0, level: RiskLevel::Deadly, food, value: 0.0 };
+            }
+            let threats: Vec<usize> = map.get_other_snakes().iter().enumerate()
+                .filter(|(_, snake)| can_meet(&head, snake.get_head()) && snake.get_head().manhattan(&cell) == 1)
+                .map(|(i, _)| i)
+                .collect();
+            // 对手也会看我方怎么走，模型学到的概率不一定可靠：至少按它在安全方向里均匀乱走来算
+            let survive: f64 = threats.iter()
+                .map(|i| {
+                    let enemy = map.get_other_snakes()[*i].get_head();
+                    let safe = Direction::iter().filter(|d| !map.strict_obstacles_check(&enemy.step(*d))).count().max(1);
+                    let p = opponents.head_distribution(*i).iter().find(|(pos, _)| *pos == cell).map_or(0.0, |(_, p)| *p);
+                    1.0 - p.max(1.0 / safe as f64)
+                })
+                .product();
+            let probability = 1.0 - survive;
+            let level = if threats.is_empty() {
+                RiskLevel::Safe
+            } else if probability < LIKELY_THRESHOLD {
+                RiskLevel::Possible
+            } else {
+                RiskLevel::Likely
+            };
+            let value = survive * (if food { 1.0 } else { 0.0 } + future);
+            MoveRisk { direction, threats, probability, level, food, value }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    fn risk_of(risks: &[MoveRisk], direction: Direction) -> &MoveRisk {
+        risks.iter().find(|risk| risk.direction == direction).unwrap()
+    }
+
+    #[test]
+    fn test_threats_and_levels() {
+        // 对手蛇头 (4,4) 与我方蛇头 (3,3) 相距 2，(3,4) 和 (4,3) 两个格子它都能走到
+        let me = snake(&[3, 3, 3, 2, 3, 1, 2, 1]);
+        let other = snake(&[4, 4, 5, 4, 6, 4, 7, 4]);
+        let foods = vec![Position::new(3, 4)];
+        let map = Map::new(me.clone(), vec![other.clone()], foods.clone(), 8);
+        let model = OpponentModel::from_snapshot(&me, &[other], &foods, 8);
+        let risks = assess_moves(&map, &model, 20);
+
+        let up = risk_of(&risks, Direction::Up);
+        assert_eq!(up.threats, vec![0]);
+        assert!(up.food);
+        // 果子也在对手旁边，贪心和冲着我方来的对手都会走过来
+        assert_eq!(up.level, RiskLevel::Likely);
+        assert_eq!(risk_of(&risks, Direction::Right).threats, vec![0]);
+        assert_eq!(risk_of(&risks, Direction::Left).level, RiskLevel::Safe);
+        assert_eq!(risk_of(&risks, Direction::Down).level, RiskLevel::Deadly);
+    }
+
+    #[test]
+    fn test_parity() {
+        // 相距 3 的蛇头永远不会同时走进同一个格子
+        let me = snake(&[3, 3, 3, 2, 3, 1, 2, 1]);
+        let other = snake(&[5, 4, 6, 4, 7, 4, 8, 4]);
+        assert!(!can_meet(me.get_head(), other.get_head()));
+        let map = Map::new(me.clone(), vec![other.clone()], vec![], 8);
+        let model = OpponentModel::from_snapshot(&me, &[other], &[], 8);
+        assert!(assess_moves(&map, &model, 20).iter().all(|risk| risk.threats.is_empty()));
+    }
+
+    #[test]
+    fn test_value_depends_on_rounds_left() {
+        let me = snake(&[3, 3, 3, 2, 3, 1, 2, 1]);
+        let other = snake(&[3, 5, 4, 5, 5, 5, 6, 5]);
+        let foods = vec![Position::new(3, 4)];
+        let map = Map::new(me.clone(), vec![other.clone()], foods.clone(), 8);
+        let model = OpponentModel::from_snapshot(&me, &[other], &foods, 8);
+
+        // 最后一回合死了也不亏，冒险去吃果子划算
+        let risks = assess_moves(&map, &model, 1);
+        assert!(risk_of(&risks, Direction::Up).value > risk_of(&risks, Direction::Left).value);
+        // 还剩很多回合时，活下来更重要
+        let risks = assess_moves(&map, &model, 30);
+        assert!(risk_of(&risks, Direction::Up).value < risk_of(&risks, Direction::Left).value);
+    }
+}
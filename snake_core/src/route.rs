@@ -0,0 +1,556 @@
+//! 吃完所有不再刷新的果子的路线规划（question.md Q2.5）
+//!
+//! 蛇长不变，果子不刷新，所以局面完全由“蛇身位置 + 已吃掉的果子”决定。
+//! 果子不多时直接在这个状态空间上做 BFS，得到的就是步数最少的路线，
+//! 顺带给出吃果子的顺序；BFS 走完仍吃不完说明不存在可行路线。
+//! 果子太多或状态太多时退化为贪心：每次沿最短路径（`find_barrier_path`）去最近（按真实步数）的果子，
+//! 贪心找不到路线时只能说没有结论（`Search::Undecided`），不能说不存在。
+//! 单个目标的可达性同样在完整蛇身的状态上 BFS，状态超过上限时同样返回 `Search::Undecided`。
+//! 暂时去不了果子时跟着自己的蛇尾兜圈子（`stall_move`），等蛇身让出路来。
+
+use crate::astar::find_barrier_path;
+use crate::map::{Direction, Map, Position, Snake};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// 超过这个数量的果子不再穷举，改用贪心
+pub const MAX_EXACT_FOODS: usize = 9;
+/// 蛇身形状每节占 2 bit 存进 u64，更长的蛇同样改用贪心
+const MAX_EXACT_LENGTH: usize = 33;
+/// `find_exact_path` 最多展开的状态数；T2 的 8x8 棋盘上长度为 4 的蛇只有不到 64 × 27 个状态，总能得出结论
+pub const MAX_EXACT_STATES: usize = 20_000;
+/// 穷举路线最多展开的状态数；8x8 棋盘上长度为 4 的蛇吃 9 个果子，状态不超过 64 × 27 × 2^9 = 884736 个，总能得出结论
+pub const MAX_ROUTE_STATES: usize = 1 << 20;
+
+/// 精确搜索的结论
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Search<T> {
+    Found(T),
+    /// 搜索完了所有状态，确实不存在
+    Impossible,
+    /// 展开的状态超过上限，没有结论
+    Undecided,
+}
+
+impl<T> Search<T> {
+    /// 找到时返回结果，否则返回 `None`
+    pub fn found(self) -> Option<T> {
+        match self {
+            Search::Found(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// 规划好的路线
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoodRoute {
+    /// 按吃掉的先后排列的果子下标（对应 `Map::get_foods`）
+    pub order: Vec<usize>,
+    /// 从当前局面开始的每一步
+    pub moves: Vec<Direction>,
+}
+
+/// 规划吃掉 `map` 中所有果子的路线
+///
+/// 果子不超过 `MAX_EXACT_FOODS` 个时穷举，返回步数最少的路线，或者确定不存在时返回 `Search::Impossible`。
+/// 果子更多、或者展开超过 `MAX_ROUTE_STATES` 个状态还没有结论时改用贪心，
+/// 这时的路线不一定最短，贪心找不到路线时返回 `Search::Undecided`
+pub fn plan_food_route(map: &Map) -> Search<FoodRoute> {
+    if map.get_foods().len() <= MAX_EXACT_FOODS && map.get_my_snake().get_body().len() <= MAX_EXACT_LENGTH
+        && let Some(route) = exact_route(map, MAX_ROUTE_STATES) {
+        return match route {
+            Some(route) => Search::Found(route),
+            None => Search::Impossible,
+        };
+    }
+    match greedy_route(map) {
+        Some(route) => Search::Found(route),
+        None => Search::Undecided,
+    }
+}
+
+/// BFS 的状态：蛇头、蛇身相对方向（每节 2 bit）、已吃掉的果子
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    head: Position,
+    shape: u64,
+    eaten: u32,
+}
+
+/// 展开超过 `max_states` 个状态时返回 `None`，否则返回穷举的结论
+fn exact_route(map: &Map, max_states: usize) -> Option<Option<FoodRoute>> {
+    let foods = map.get_foods();
+    let all_eaten = (1u32 << foods.len()) - 1;
+    let start_snake = map.get_my_snake().clone();
+    let start = State { head: *start_snake.get_head(), shape: encode_shape(&start_snake), eaten: 0 };
+
+    let mut parents: HashMap<State, (State, Direction)> = HashMap::new();
+    let mut queue: VecDeque<(State, Snake)> = VecDeque::new();
+    queue.push_back((start, start_snake));
+
+    while let Some((state, snake)) = queue.pop_front() {
+        if state.eaten == all_eaten {
+            return Some(Some(rebuild_route(&parents, start, state, foods)));
+        }
+        for direction in Direction::iter() {
+            let head = snake.get_head().step(direction);
+            if blocked(map, &snake, &head) {
+                continue;
+            }
+            let next_snake = snake.moved(direction);
+            let mut eaten = state.eaten;
+            if let Some(index) = foods.iter().position(|food| *food == head) {
+                eaten |= 1 << index;
+            }
+            let next = State { head, shape: encode_shape(&next_snake), eaten };
+            if next == start || parents.contains_key(&next) {
+                continue;
+            }
+            if parents.len() >= max_states {
+                return None;
+            }
+            parents.insert(next, (state, direction));
+            queue.push_back((next, next_snake));
+        }
+    }
+
+    Some(None)
+}
+
+/// 我方蛇头走到 `goal` 的最短路线
+///
+/// 在完整的蛇身状态上 BFS，蛇尾让出和新走过的格子变成蛇身都按真实规则处理，
+/// 所以返回 `Search::Impossible` 当且仅当不存在能走到 `goal` 的走法。状态数随蛇长指数增长，
+/// 展开超过 `MAX_EXACT_STATES` 个状态还没有结论时返回 `Search::Undecided`
+pub fn find_exact_path(map: &Map, goal: &Position) -> Search<Vec<Direction>> {
+    exact_path(map, goal, MAX_EXACT_STATES)
+}
+
+fn exact_path(map: &Map, goal: &Position, max_states: usize) -> Search<Vec<Direction>> {
+    let start = map.get_my_snake().clone();
+    if start.get_head() == goal {
+        return Search::Found(vec![]);
+    }
+    let mut parents: HashMap<Vec<Position>, (Vec<Position>, Direction)> = HashMap::new();
+    let mut queue: VecDeque<Snake> = VecDeque::new();
+    queue.push_back(start.clone());
+
+    while let Some(snake) = queue.pop_front() {
+        for direction in Direction::iter() {
+            let head = snake.get_head().step(direction);
+            if blocked(map, &snake, &head) {
+                continue;
+            }
+            let next = snake.moved(direction);
+            if next.get_body() == start.get_body() || parents.contains_key(next.get_body()) {
+                continue;
+            }
+            if parents.len() >= max_states {
+                return Search::Undecided;
+            }
+            parents.insert(next.get_body().to_vec(), (snake.get_body().to_vec(), direction));
+            if head == *goal {
+                let mut moves = Vec::new();
+                let mut body = next.get_body().to_vec();
+                while body != start.get_body() {
+                    let (parent, direction) = &parents[&body];
+                    moves.push(*direction);
+                    body = parent.clone();
+                }
+                moves.reverse();
+                return Search::Found(moves);
+            }
+            queue.push_back(next);
+        }
+    }
+
+    Search::Impossible
+}
+
+fn greedy_route(map: &Map) -> Option<FoodRoute> {
+    let foods = map.get_foods().clone();
+    let mut snake = map.get_my_snake().clone();
+    let mut remaining: Vec<usize> = (0..foods.len()).collect();
+    let mut route = FoodRoute { order: vec![], moves: vec![] };
+    let all: Vec<Direction> = Direction::iter().collect();
+    // 连续兜圈子的步数，绕满整个棋盘还去不了任何果子就放弃
+    let mut stalled = 0;
+
+    while !remaining.is_empty() {
+        // 只把还没吃掉的果子放进场地
+        let left: Vec<Position> = remaining.iter().map(|i| foods[*i]).collect();
+        let current = Map::with_barriers(snake.clone(), left.clone(), map.get_barriers().clone(), map.get_size());
+        let path = left.iter()
+            .map(|food| find_barrier_path(&current, food))
+            .filter(|path| !path.is_empty())
+            .min_by_key(|path| path.len());
+        let path = match path {
+            Some(path) => {
+                stalled = 0;
+                path
+            }
+            None if stalled < map.get_size() * map.get_size() => {
+                stalled += 1;
+                vec![stall_move(&current, &all)?]
+            }
+            None => return None,
+        };
+
+        for direction in &path {
+            let head = snake.get_head().step(*direction);
+            // 路上顺带经过的果子也算吃掉
+            if let Some(i) = remaining.iter().position(|i| foods[*i] == head) {
+                route.order.push(remaining.remove(i));
+            }
+            snake = snake.moved(*direction);
+            route.moves.push(*direction);
+        }
+    }
+
+    Some(route)
+}
+
+/// 暂时去不了果子时兜圈子的一步：跟着自己的蛇尾走
+///
+/// 只在 `allowed` 中挑选。蛇尾每回合都会往前让出一格，走完这一步后还能走到蛇尾，就能一直跟着它活下去；
+/// 这样的方向里优先选离蛇尾最远的，绕的圈子大，中途让出的格子也多，再一样时选活动空间大的。
+/// 没有这样的方向时返回 `None`
+pub fn stall_move(map: &Map, allowed: &[Direction]) -> Option<Direction> {
+    let head = *map.get_my_snake().get_head();
+    allowed.iter()
+        .copied()
+        .filter(|direction| !map.strict_obstacles_check(&head.step(*direction)))
+        .filter_map(|direction| {
+            let distance = tail_distance(map, direction)?;
+            Some((direction, distance, map.reachable_area(&head.step(direction), 1, usize::MAX)))
+        })
+        .max_by_key(|(_, distance, area)| (*distance, *area))
+        .map(|(direction, _, _)| direction)
+}
+
+/// 按真实规则沿 `path` 走一遍（蛇身跟着移动），途中不会撞死
+pub fn is_walkable(map: &Map, path: &[Direction]) -> bool {
+    let mut snake = map.get_my_snake().clone();
+    path.iter().all(|direction| {
+        let head = snake.get_head().step(*direction);
+        let safe = !blocked(map, &snake, &head);
+        snake = snake.moved(*direction);
+        safe
+    })
+}
+
+/// 蛇头朝 `direction` 走一步后是否还能走到自己的蛇尾
+pub fn keeps_tail_reachable(map: &Map, direction: Direction) -> bool {
+    !map.strict_obstacles_check(&map.get_my_snake().get_head().step(direction)) && tail_distance(map, direction).is_some()
+}
+
+/// 蛇头朝 `direction` 走一步后，再走到那时的蛇尾最少要几步
+///
+/// 只按 `Map::strict_obstacles_check_at` 判断通行，不考虑路上新长出来的蛇身
+fn tail_distance(map: &Map, direction: Direction) -> Option<i32> {
+    let start = map.get_my_snake().get_head().step(direction);
+    let tail = *map.get_my_snake().moved(direction).get_tail();
+    if start == tail {
+        return Some(0);
+    }
+    let mut visited = HashSet::from([start]);
+    let mut frontier = vec![start];
+    let mut steps = 0;
+    while !frontier.is_empty() {
+        steps += 1;
+        let mut next = Vec::new();
+        for pos in &frontier {
+            for direction in Direction::iter() {
+                let neighbor = pos.step(direction);
+                if neighbor == tail {
+                    return Some(steps);
+                }
+                // 走完第一步之后的第 k 步，相对于 `map` 中的局面是第 k + 1 步
+                if visited.contains(&neighbor) || map.strict_obstacles_check_at(&neighbor, steps + 1) {
+                    continue;
+                }
+                visited.insert(neighbor);
+                next.push(neighbor);
+            }
+        }
+        frontier = next;
+    }
+    None
+}
+
+/// 蛇头走到 `head` 是否会撞死：出界、障碍物，或者移动后的自己的身体
+fn blocked(map: &Map, snake: &Snake, head: &Position) -> bool {
+    !map.in_board(head) || map.get_barriers().contains(head) || snake.get_without_tail().contains(head)
+}
+
+/// 从蛇头开始，记录每一节身体相对于前一节的方向
+fn encode_shape(snake: &Snake) -> u64 {
+    let mut shape = 0u64;
+    for pair in snake.get_body().windows(2) {
+        let direction = Direction::iter().find(|d| pair[0].step(*d) == pair[1]).unwrap_or(Direction::Up);
+        shape = (shape << 2) | direction.to_code() as u64;
+    }
+    shape
+}
+
+fn rebuild_route(parents: &HashMap<State, (State, Direction)>, start: State, goal: State, foods: &[Position]) -> FoodRoute {
+    let mut moves = Vec::new();
+    let mut order = Vec::new();
+    let mut state = goal;
+    while state != start {
+        let (parent, direction) = parents[&state];
+        if parent.eaten != state.eaten {
+            order.push((state.eaten ^ parent.eaten).trailing_zeros() as usize);
+        }
+        moves.push(direction);
+        state = parent;
+    }
+    moves.reverse();
+    order.reverse();
+    debug_assert_eq!(order.len(), foods.len());
+    FoodRoute { order, moves }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{positions, snake};
+    use crate::random::RandomGenerator;
+
+    /// 按路线走一遍，确认不会撞死并且吃完所有果子
+    fn replay(map: &Map, route: &FoodRoute) {
+        let mut snake = map.get_my_snake().clone();
+        let mut eaten = vec![];
+        for direction in &route.moves {
+            let head = snake.get_head().step(*direction);
+            assert!(!blocked(map, &snake, &head), "snake dies at {:?}", head);
+            snake = snake.moved(*direction);
+            if let Some(i) = map.get_foods().iter().position(|food| *food == head)
+                && !eaten.contains(&i) {
+                eaten.push(i);
+            }
+        }
+        assert_eq!(eaten, route.order);
+        assert_eq!(eaten.len(), map.get_foods().len());
+    }
+
+    #[test]
+    fn test_order_uses_real_distance() {
+        // (1,6) 离蛇头的曼哈顿距离更近，但被障碍挡住要绕远，应该先吃 (6,2)
+        let barriers = positions(&[1, 5, 2, 5, 2, 6, 2, 7]);
+        let foods = positions(&[1, 6, 6, 2]);
+        let map = Map::with_barriers(snake(&[3, 4, 4, 4, 5, 4, 6, 4]), foods, barriers, 8);
+        let route = plan_food_route(&map).found().unwrap();
+        replay(&map, &route);
+        assert_eq!(route.order, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_route_is_shortest() {
+        let foods = positions(&[4, 1, 6, 1, 8, 1]);
+        let map = Map::with_barriers(snake(&[2, 1, 1, 1, 1, 2, 1, 3]), foods, vec![], 8);
+        let route = plan_food_route(&map).found().unwrap();
+        replay(&map, &route);
+        assert_eq!(route.moves, vec![Direction::Right; 6]);
+        assert_eq!(route.order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_no_route() {
+        // (8,8) 被障碍围住
+        let barriers = positions(&[7, 8, 8, 7]);
+        let foods = positions(&[3, 3, 8, 8]);
+        let map = Map::with_barriers(snake(&[1, 1, 1, 2, 1, 3, 1, 4]), foods, barriers, 8);
+        assert_eq!(plan_food_route(&map), Search::Impossible);
+    }
+
+    /// 暴力枚举不超过 `depth` 步的所有走法，看能否走到 `goal`
+    fn brute_force_reachable(map: &Map, snake: &Snake, goal: &Position, depth: usize) -> bool {
+        if snake.get_head() == goal {
+            return true;
+        }
+        depth > 0 && Direction::iter().any(|direction| {
+            let head = snake.get_head().step(direction);
+            !blocked(map, snake, &head) && brute_force_reachable(map, &snake.moved(direction), goal, depth - 1)
+        })
+    }
+
+    #[test]
+    fn test_stall_follows_tail() {
+        // 往上走会钻进左上角只有 2 格的死胡同，往右离蛇尾最远
+        let barriers = positions(&[2, 3, 2, 4]);
+        let map = Map::with_barriers(snake(&[1, 2, 1, 1, 2, 1, 3, 1]), vec![], barriers, 4);
+        assert!(!keeps_tail_reachable(&map, Direction::Up));
+        assert!(keeps_tail_reachable(&map, Direction::Right));
+        assert_eq!(stall_move(&map, &[Direction::Up, Direction::Right]), Some(Direction::Right));
+        assert_eq!(stall_move(&map, &[Direction::Up]), None);
+
+        // 一直兜圈子不会死
+        let map = Map::with_barriers(snake(&[2, 2, 2, 3, 3, 3, 3, 2]), vec![], vec![], 4);
+        let all: Vec<Direction> = Direction::iter().collect();
+        let mut current = map.get_my_snake().clone();
+        for _ in 0..50 {
+            let here = Map::with_barriers(current.clone(), vec![], vec![], 4);
+            let direction = stall_move(&here, &all).unwrap();
+            assert!(!blocked(&here, &current, &current.get_head().step(direction)));
+            current = current.moved(direction);
+        }
+    }
+
+    #[test]
+    fn test_exact_path_needs_tail_to_move() {
+        // (1,1) 只能从蛇尾所在的 (1,2) 进去，要等蛇身让出后绕过去，最少 5 步
+        let map = Map::with_barriers(snake(&[3, 2, 2, 2, 1, 2]), vec![], positions(&[2, 1]), 4);
+        let path = find_exact_path(&map, &Position::new(1, 1)).found().unwrap();
+        assert_eq!(path.len(), 5);
+        // 蛇头三面被堵，第一步就会撞死
+        let map = Map::with_barriers(snake(&[1, 2, 2, 2, 2, 3, 2, 4]), vec![], positions(&[1, 1, 1, 3]), 4);
+        assert_eq!(find_exact_path(&map, &Position::new(4, 4)), Search::Impossible);
+    }
+
+    #[test]
+    fn test_exact_path_gives_up() {
+        // 14 节的蛇，(10,10) 被障碍围住；蛇身状态太多，精确搜索没有结论，不能当成走不到
+        let mut coords = vec![4, 2, 3, 2, 2, 2, 1, 2];
+        coords.extend((1..=10).flat_map(|x| [x, 1]));
+        let map = Map::with_barriers(snake(&coords), vec![], positions(&[9, 10, 10, 9]), 10);
+        assert_eq!(find_exact_path(&map, &Position::new(10, 10)), Search::Undecided);
+    }
+
+    #[test]
+    fn test_exact_path_matches_brute_force() {
+        const DEPTH: usize = 8;
+        let mut rng = RandomGenerator::new(13);
+        let mut checked = 0;
+        while checked < 300 {
+            let size = 4;
+            let cell = |rng: &mut RandomGenerator| Position::new(
+                (rng.generate_float(1.0, size as f64 + 1.0) as i32).min(size),
+                (rng.generate_float(1.0, size as f64 + 1.0) as i32).min(size));
+            // 随机游走出一条长度为 3 或 4 的蛇，走进死角就重来
+            let length = 3 + rng.generate_bool() as usize;
+            let mut body = vec![cell(&mut rng)];
+            while body.len() < length {
+                let last = body[body.len() - 1];
+                let next: Vec<Position> = Direction::iter().map(|d| last.step(d))
+                    .filter(|pos| (1..=size).contains(&pos.get_x()) && (1..=size).contains(&pos.get_y()) && !body.contains(pos))
+                    .collect();
+                if next.is_empty() {
+                    break;
+                }
+                body.push(next[(rng.generate_float(0.0, next.len() as f64) as usize).min(next.len() - 1)]);
+            }
+            let mut barriers: Vec<Position> = Vec::new();
+            for _ in 0..3 {
+                let pos = cell(&mut rng);
+                if !body.contains(&pos) && !barriers.contains(&pos) {
+                    barriers.push(pos);
+                }
+            }
+            let goal = cell(&mut rng);
+            if body.len() < length || body.contains(&goal) || barriers.contains(&goal) {
+                continue;
+            }
+            let map = Map::with_barriers(Snake::from_body(body), vec![], barriers, size);
+            assert_eq!(map.validate(), vec![]);
+
+            let path = find_exact_path(&map, &goal).found();
+            let within_depth = path.as_ref().is_some_and(|path| path.len() <= DEPTH);
+            assert_eq!(within_depth, brute_force_reachable(&map, map.get_my_snake(), &goal, DEPTH), "{:?} -> {:?}", map, goal);
+            if let Some(path) = path {
+                let mut snake = map.get_my_snake().clone();
+                for direction in path {
+                    assert!(!blocked(&map, &snake, &snake.get_head().step(direction)));
+                    snake = snake.moved(direction);
+                }
+                assert_eq!(*snake.get_head(), goal);
+            }
+            checked += 1;
+        }
+    }
+
+    /// 不限状态数，直接在（完整蛇身，已吃掉的果子）上 BFS，返回吃完所有果子的最少步数
+    fn brute_force_route_length(map: &Map) -> Option<usize> {
+        let foods = map.get_foods();
+        let all_eaten = (1u32 << foods.len()) - 1;
+        let start = (map.get_my_snake().clone(), 0u32);
+        let mut seen: HashSet<(Vec<Position>, u32)> = HashSet::from([(start.0.get_body().to_vec(), 0)]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some(((snake, eaten), steps)) = queue.pop_front() {
+            if eaten == all_eaten {
+                return Some(steps);
+            }
+            for direction in Direction::iter() {
+                let head = snake.get_head().step(direction);
+                if blocked(map, &snake, &head) {
+                    continue;
+                }
+                let eaten = foods.iter().position(|food| *food == head).map_or(eaten, |i| eaten | 1 << i);
+                let next = snake.moved(direction);
+                if seen.insert((next.get_body().to_vec(), eaten)) {
+                    queue.push_back(((next, eaten), steps + 1));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_route_with_nine_foods() {
+        // 8x8 棋盘、12 个障碍、9 个果子：状态数超过 MAX_EXACT_STATES，路线仍然要精确
+        let size = 8;
+        let mut rng = RandomGenerator::new(5);
+        let cell = |rng: &mut RandomGenerator| Position::new(
+            (rng.generate_float(1.0, size as f64 + 1.0) as i32).min(size),
+            (rng.generate_float(1.0, size as f64 + 1.0) as i32).min(size));
+        let mut checked = 0;
+        while checked < 2 {
+            let head = cell(&mut rng);
+            let mut body = vec![head];
+            for _ in 0..3 {
+                let last = body[body.len() - 1];
+                if let Some(next) = Direction::iter().map(|d| last.step(d))
+                    .find(|pos| (1..=size).contains(&pos.get_x()) && (1..=size).contains(&pos.get_y()) && !body.contains(pos)) {
+                    body.push(next);
+                }
+            }
+            let mut used = body.clone();
+            let mut pick = |count: usize, rng: &mut RandomGenerator| {
+                let mut picked = vec![];
+                while picked.len() < count {
+                    let pos = cell(rng);
+                    if !used.contains(&pos) {
+                        used.push(pos);
+                        picked.push(pos);
+                    }
+                }
+                picked
+            };
+            let barriers = pick(12, &mut rng);
+            let foods = pick(9, &mut rng);
+            if body.len() < 4 {
+                continue;
+            }
+            let map = Map::with_barriers(Snake::from_body(body), foods, barriers, size);
+            if exact_route(&map, MAX_EXACT_STATES).is_some() {
+                continue;
+            }
+            let route = plan_food_route(&map);
+            assert_ne!(route, Search::Undecided);
+            let route = route.found();
+            assert_eq!(route.as_ref().map(|route| route.moves.len()), brute_force_route_length(&map), "{:?}", map);
+            if let Some(route) = route {
+                replay(&map, &route);
+                checked += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_greedy_for_many_foods() {
+        let foods = positions(&[2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 2, 8, 8, 2, 5, 1]);
+        let map = Map::with_barriers(snake(&[1, 1, 1, 2, 1, 3, 1, 4]), foods, vec![], 8);
+        let route = plan_food_route(&map).found().unwrap();
+        replay(&map, &route);
+    }
+}
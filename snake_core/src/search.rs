@@ -0,0 +1,283 @@
+//! T3 的同时行动对抗搜索（paranoid）
+//!
+//! 局面用 `BoardState` 按真实规则推进（蛇尾让出、头对头同归于尽），
+//! 对手被看成一个联合起来与我方作对的整体：每一层我方先选方向，再取所有对手联合动作中
+//! 对我方最坏的结果，用 alpha-beta 剪枝。深度从 1 开始迭代加深，超时后使用最后一个
+//! 完整搜完的深度的结果；按前两层的耗时估计下一层来不及搜完时，也直接停下。
+//! 新刷出的果子无法预知，搜索中只计算场上已有的果子。
+
+use crate::bitboard::{BoardState, MAX_SNAKES};
+use crate::map::{Direction, Position, Snake};
+
+/// 我方死亡的估值，死得越晚估值越高
+pub const LOSS: i32 = -1_000_000;
+/// 每吃到一个果子的估值
+const FOOD_VALUE: i32 = 100;
+/// 每死掉一条对手的估值
+const KILL_VALUE: i32 = 30;
+/// 活动空间的估值上限（格数），再大的空间都一样安全
+const SPACE_CAP: usize = 12;
+/// 每搜索这么多个节点检查一次是否超时
+const CHECK_INTERVAL: u32 = 256;
+
+/// 搜索用的局面，第 0 条蛇是我方
+#[derive(Clone, Copy, Debug)]
+pub struct SearchState {
+    board: BoardState,
+    /// 包含当前回合在内的剩余回合数
+    rounds_left: i32,
+    /// 从根局面开始每条蛇吃到的果子数
+    eaten: [i32; MAX_SNAKES],
+}
+
+/// 迭代加深的结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    /// 完整搜完的最大深度
+    pub depth: i32,
+    /// 我方每个方向在该深度下的最坏情况估值
+    pub values: Vec<(Direction, i32)>,
+}
+
+impl SearchResult {
+    /// 估值最高的所有方向
+    pub fn best_directions(&self) -> Vec<Direction> {
+        let best = self.values.iter().map(|(_, value)| *value).max().unwrap_or(LOSS);
+        self.values.iter().filter(|(_, value)| *value == best).map(|(direction, _)| *direction).collect()
+    }
+}
+
+impl SearchState {
+    /// 棋盘边长、蛇数和蛇长超出 `BoardState::fits` 支持的范围时 panic，这时应改用 `try_new`
+    pub fn new(my_snake: Snake, other_snakes: Vec<Snake>, foods: Vec<Position>, size: i32, rounds_left: i32) -> SearchState {
+        let mut snakes = vec![my_snake];
+        snakes.extend(other_snakes);
+        let board = BoardState::new(size, &snakes, &foods, &[]);
+        SearchState { board, rounds_left, eaten: [0; MAX_SNAKES] }
+    }
+
+    /// 与 `new` 相同，局面放不进位棋盘时返回 `None`
+    pub fn try_new(my_snake: Snake, other_snakes: Vec<Snake>, foods: Vec<Position>, size: i32, rounds_left: i32) -> Option<SearchState> {
+        let fits = BoardState::fits(size, &[std::slice::from_ref(&my_snake), &other_snakes].concat());
+        fits.then(|| SearchState::new(my_snake, other_snakes, foods, size, rounds_left))
+    }
+
+    pub fn get_board(&self) -> &BoardState {
+        &self.board
+    }
+
+    pub fn get_rounds_left(&self) -> i32 {
+        self.rounds_left
+    }
+
+    /// 每条蛇从根局面开始吃到的果子数
+    pub fn get_eaten(&self) -> &[i32] {
+        &self.eaten[..self.board.snake_count()]
+    }
+
+    /// 我方死亡或者没有剩余回合
+    pub fn is_terminal(&self) -> bool {
+        !self.board.is_alive(0) || self.rounds_left <= 0
+    }
+
+    /// 所有蛇同时按 `actions` 走一步，`actions` 与蛇一一对应，死蛇的动作被忽略
+    pub fn next(&self, actions: &[Direction]) -> SearchState {
+        let mut next = *self;
+        next.rounds_left -= 1;
+        let moves: Vec<Option<Direction>> = actions.iter().copied().map(Some).collect();
+        let outcome = next.board.apply_joint_moves(&moves);
+        for (i, eaten) in next.eaten.iter_mut().enumerate() {
+            *eaten += (outcome.ate >> i & 1) as i32;
+        }
+        next
+    }
+
+    /// 第 `i` 条蛇不会立刻撞死的方向；无路可走时返回一个方向，让它在结算中死掉
+    pub fn candidate_moves(&self, i: usize) -> Vec<Direction> {
+        let moves: Vec<Direction> = Direction::iter().filter(|direction| !self.board.is_deadly(i, *direction)).collect();
+        if moves.is_empty() { vec![Direction::Up] } else { moves }
+    }
+
+    /// 所有对手候选方向的笛卡尔积，每个元素是一组完整的 `actions`（第 0 位留给我方）
+    fn opponent_joint_moves(&self, my_move: Direction) -> Vec<Vec<Direction>> {
+        let mut joints = vec![vec![my_move]];
+        for i in 1..self.board.snake_count() {
+            let moves = self.candidate_moves(i);
+            joints = joints.into_iter()
+                .flat_map(|joint| moves.iter().map(move |direction| {
+                    let mut joint = joint.clone();
+                    joint.push(*direction);
+                    joint
+                }))
+                .collect();
+        }
+        joints
+    }
+
+    /// 叶子局面的估值：吃到的果子、死掉的对手，以及蛇头还能活动的空间
+    fn evaluate(&self, ply: i32) -> i32 {
+        let Some(head) = self.board.head(0) else {
+            return LOSS + ply;
+        };
+        let killed = (self.board.snake_count() - self.board.alive_count()) as i32;
+        let space = Direction::iter()
+            .map(|direction| self.board.reachable_area(0, &head.step(direction), 1, SPACE_CAP))
+            .max()
+            .unwrap_or(0);
+        self.eaten[0] * FOOD_VALUE + killed * KILL_VALUE + space as i32
+    }
+}
+
+struct Searcher<'a> {
+    now: &'a dyn Fn() -> f64,
+    /// 超过这个时刻就放弃当前深度，`None` 表示不限时
+    deadline: Option<f64>,
+    nodes: u32,
+    aborted: bool,
+}
+
+impl Searcher<'_> {
+    fn check_time(&mut self) -> bool {
+        self.nodes += 1;
+        if let Some(deadline) = self.deadline
+            && !self.aborted && self.nodes.is_multiple_of(CHECK_INTERVAL) && (self.now)() > deadline {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    /// 我方选方向的一层，返回 `None` 表示超时
+    fn max_value(&mut self, state: &SearchState, depth: i32, ply: i32, alpha: i32, beta: i32) -> Option<i32> {
+        if self.check_time() {
+            return None;
+        }
+        if depth == 0 || state.is_terminal() {
+            return Some(state.evaluate(ply));
+        }
+        let mut best = LOSS - 1;
+        for my_move in state.candidate_moves(0) {
+            let value = self.min_value(state, my_move, depth, ply, alpha.max(best), beta)?;
+            best = best.max(value);
+            if best >= beta {
+                break;
+            }
+        }
+        Some(best)
+    }
+
+    /// 我方方向已定，对手取最坏联合动作的一层
+    fn min_value(&mut self, state: &SearchState, my_move: Direction, depth: i32, ply: i32, alpha: i32, beta: i32) -> Option<i32> {
+        let mut worst = i32::MAX;
+        for joint in state.opponent_joint_moves(my_move) {
+            let value = self.max_value(&state.next(&joint), depth - 1, ply + 1, alpha, beta.min(worst))?;
+            worst = worst.min(value);
+            if worst <= alpha {
+                break;
+            }
+        }
+        Some(worst)
+    }
+}
+
+/// 从 `root` 开始迭代加深，深度不超过 `max_depth` 和剩余回合数
+///
+/// `now` 返回当前时间（毫秒），搜索在 `budget_ms` 内结束。深度 1 总会搜完，
+/// 立刻撞死的方向直接记为 `LOSS`
+pub fn search(root: &SearchState, max_depth: i32, budget_ms: f64, now: &dyn Fn() -> f64) -> SearchResult {
+    let start = now();
+    let deadline = start + budget_ms;
+    let candidates = root.candidate_moves(0);
+    let losing = |direction: &Direction| !candidates.contains(direction) || root.is_terminal();
+    let mut result = SearchResult { depth: 0, values: Direction::iter().map(|d| (d, LOSS)).collect() };
+    let max_depth = max_depth.min(root.rounds_left);
+
+    let mut last_cost = 0.0;
+    for depth in 1..=max_depth {
+        let depth_start = now();
+        if depth > 1 && depth_start >= deadline {
+            break;
+        }
+        let mut searcher = Searcher { now, deadline: (depth > 1).then_some(deadline), nodes: 0, aborted: false };
+        let mut values = Vec::new();
+        for direction in Direction::iter() {
+            let value = if losing(&direction) {
+                Some(LOSS)
+            } else {
+                // 根节点的每个方向都用完整窗口，保证估值准确，方便调用方在同分方向中再挑选
+                searcher.min_value(root, direction, depth, 0, LOSS - 1, i32::MAX)
+            };
+            match value {
+                Some(value) => values.push((direction, value)),
+                None => break,
+            }
+        }
+        if searcher.aborted {
+            break;
+        }
+        result = SearchResult { depth, values };
+
+        // 每加深一层的耗时大致按固定倍数增长，预计下一层搜不完就不再开始
+        let cost = now() - depth_start;
+        if last_cost > 0.0 && now() + cost * (cost / last_cost) > deadline {
+            break;
+        }
+        last_cost = cost;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    fn value_of(result: &SearchResult, direction: Direction) -> i32 {
+        result.values.iter().find(|(d, _)| *d == direction).unwrap().1
+    }
+
+    #[test]
+    fn test_avoid_head_on() {
+        // 对手蛇头在 (3,3)，我方向右走到 (2,3) 时对手也能走到那里
+        let me = snake(&[1, 3, 1, 2, 1, 1, 2, 1]);
+        let other = snake(&[3, 3, 4, 3, 5, 3, 5, 4]);
+        let root = SearchState::new(me, vec![other], vec![], 5, 50);
+        let result = search(&root, 1, 0.0, &|| 0.0);
+        // 最坏情况是同归于尽
+        assert_eq!(value_of(&result, Direction::Right), LOSS + 1);
+        // 向左出界、向下撞上自己
+        assert_eq!(value_of(&result, Direction::Left), LOSS);
+        assert_eq!(value_of(&result, Direction::Down), LOSS);
+        assert_eq!(result.best_directions(), vec![Direction::Up]);
+    }
+
+    #[test]
+    fn test_tail_moves_away() {
+        // 蛇尾 (2,2) 下一步会让出，可以跟着蛇尾走
+        let me = snake(&[1, 2, 1, 1, 2, 1, 2, 2]);
+        let root = SearchState::new(me, vec![], vec![], 5, 50);
+        let result = search(&root, 3, f64::INFINITY, &|| 0.0);
+        assert!(value_of(&result, Direction::Right) > LOSS + 3);
+    }
+
+    #[test]
+    fn test_prefers_safe_food() {
+        let me = snake(&[3, 1, 2, 1, 1, 1, 1, 2]);
+        let foods = vec![Position::new(4, 1)];
+        let root = SearchState::new(me, vec![], foods, 5, 50);
+        let result = search(&root, 2, f64::INFINITY, &|| 0.0);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.best_directions(), vec![Direction::Right]);
+    }
+
+    #[test]
+    fn test_timeout_keeps_last_complete_depth() {
+        let me = snake(&[3, 3, 3, 2, 3, 1, 2, 1]);
+        let other = snake(&[5, 5, 5, 4, 5, 3, 5, 2]);
+        let root = SearchState::new(me, vec![other], vec![], 5, 50);
+        let result = search(&root, 10, 0.0, &|| 0.0);
+        assert_eq!(result.depth, 1);
+        // 不超过剩余回合数
+        let root = SearchState::new(snake(&[3, 3, 3, 2, 3, 1, 2, 1]), vec![], vec![], 5, 2);
+        assert_eq!(search(&root, 10, f64::INFINITY, &|| 0.0).depth, 2);
+    }
+}
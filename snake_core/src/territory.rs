@@ -0,0 +1,135 @@
+//! 按“谁先到”划分棋盘（Voronoi 领地）
+//!
+//! 从所有蛇头同时开始多源 BFS，每个格子归最先走到它的蛇。几条蛇同时走到的格子算作争夺中的格子，
+//! 不归任何一方，也不再从那里往外扩展——同时走到就是头对头，按规则两条蛇都会死。
+//! 蛇身按 `Snake::occupies_after` 逐步让出，障碍物始终不能通行。
+
+use crate::map::{Direction, Map, Position};
+use std::collections::VecDeque;
+
+/// 一个格子的归属
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Owner {
+    /// 第 i 条蛇最先到达，我方为 0，其他蛇按 `Map::get_other_snakes` 的顺序从 1 开始
+    Snake(usize),
+    /// 几条蛇同时到达
+    Contested,
+    /// 谁都到不了
+    Unreachable,
+}
+
+pub struct Territory {
+    size: i32,
+    owners: Vec<Owner>,
+    /// 最先到达的步数，到不了为 `None`
+    distances: Vec<Option<i32>>,
+}
+
+impl Territory {
+    pub fn compute(map: &Map) -> Territory {
+        let size = map.get_size();
+        let cells = (size * size) as usize;
+        let mut owners = vec![Owner::Unreachable; cells];
+        let mut distances = vec![None; cells];
+        let snakes: Vec<_> = std::iter::once(map.get_my_snake()).chain(map.get_other_snakes()).collect();
+        let index = |pos: &Position| ((pos.get_y() - 1) * size + pos.get_x() - 1) as usize;
+        let blocked = |pos: &Position, steps: i32| {
+            !map.in_board(pos) || map.get_barriers().contains(pos) || snakes.iter().any(|snake| snake.occupies_after(pos, steps))
+        };
+
+        let mut frontier: VecDeque<Position> = VecDeque::new();
+        for (i, snake) in snakes.iter().enumerate() {
+            let head = snake.get_head();
+            if map.in_board(head) {
+                owners[index(head)] = Owner::Snake(i);
+                distances[index(head)] = Some(0);
+                frontier.push_back(*head);
+            }
+        }
+
+        // 一层一层扩展：先收集这一层每个格子被谁走到，再统一定下归属
+        let mut steps = 0;
+        while !frontier.is_empty() {
+            steps += 1;
+            let mut reached: Vec<(Position, Owner)> = Vec::new();
+            for pos in frontier.drain(..) {
+                let Owner::Snake(i) = owners[index(&pos)] else {
+                    continue;
+                };
+                for direction in Direction::iter() {
+                    let next = pos.step(direction);
+                    if blocked(&next, steps) || distances[index(&next)].is_some() {
+                        continue;
+                    }
+                    match reached.iter_mut().find(|(cell, _)| *cell == next) {
+                        Some((_, owner)) if *owner != Owner::Snake(i) => *owner = Owner::Contested,
+                        Some(_) => {}
+                        None => reached.push((next, Owner::Snake(i))),
+                    }
+                }
+            }
+            for (pos, owner) in reached {
+                owners[index(&pos)] = owner;
+                distances[index(&pos)] = Some(steps);
+                frontier.push_back(pos);
+            }
+        }
+
+        Territory { size, owners, distances }
+    }
+
+    fn index(&self, pos: &Position) -> Option<usize> {
+        let in_board = pos.get_x() >= 1 && pos.get_x() <= self.size && pos.get_y() >= 1 && pos.get_y() <= self.size;
+        in_board.then(|| ((pos.get_y() - 1) * self.size + pos.get_x() - 1) as usize)
+    }
+
+    /// 棋盘外的格子返回 `Owner::Unreachable`
+    pub fn owner(&self, pos: &Position) -> Owner {
+        self.index(pos).map_or(Owner::Unreachable, |i| self.owners[i])
+    }
+
+    /// 最先到达 `pos` 的步数
+    pub fn distance(&self, pos: &Position) -> Option<i32> {
+        self.index(pos).and_then(|i| self.distances[i])
+    }
+
+    /// 第 `i` 条蛇控制的格子数，包括它的蛇头
+    pub fn area(&self, i: usize) -> usize {
+        self.owners.iter().filter(|owner| **owner == Owner::Snake(i)).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snake;
+
+    #[test]
+    fn test_split_and_contested() {
+        // 两个蛇头在同一行，相隔 4 格，正中间的一列同时到达
+        let me = snake(&[1, 3, 1, 2, 1, 1, 2, 1]);
+        let other = snake(&[5, 3, 5, 4, 5, 5, 4, 5]);
+        let map = Map::new(me, vec![other], vec![], 5);
+        let territory = Territory::compute(&map);
+        assert_eq!(territory.owner(&Position::new(2, 3)), Owner::Snake(0));
+        assert_eq!(territory.owner(&Position::new(4, 3)), Owner::Snake(1));
+        assert_eq!(territory.owner(&Position::new(3, 3)), Owner::Contested);
+        assert_eq!(territory.distance(&Position::new(3, 3)), Some(2));
+        assert_eq!(territory.owner(&Position::new(6, 3)), Owner::Unreachable);
+        // 整列 x=3 都是同时到达
+        assert!((1..=5).all(|y| territory.owner(&Position::new(3, y)) == Owner::Contested));
+        assert_eq!(territory.area(0), 10);
+        assert_eq!(territory.area(1), 10);
+    }
+
+    #[test]
+    fn test_body_frees_over_time() {
+        // 蛇盘成一个正方形，第 k 节要第 4 - k 步才让出
+        let map = Map::with_barriers(snake(&[2, 2, 2, 3, 3, 3, 3, 2]), vec![], vec![], 4);
+        let territory = Territory::compute(&map);
+        assert_eq!(territory.distance(&Position::new(3, 2)), Some(1));
+        assert_eq!(territory.distance(&Position::new(3, 3)), Some(2));
+        assert_eq!(territory.distance(&Position::new(2, 3)), Some(3));
+        assert_eq!(territory.area(0), 16);
+    }
+}
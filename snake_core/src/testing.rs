@@ -0,0 +1,12 @@
+//! 各模块测试共用的局面构造函数，坐标按 `[x0, y0, x1, y1, ...]` 依次排列
+
+use crate::map::{Position, Snake};
+
+/// 按坐标构造蛇身，第一对坐标是蛇头
+pub fn snake(coords: &[i32]) -> Snake {
+    Snake::from_body(positions(coords))
+}
+
+pub fn positions(coords: &[i32]) -> Vec<Position> {
+    coords.chunks(2).map(|pair| Position::new(pair[0], pair[1])).collect()
+}